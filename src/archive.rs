@@ -0,0 +1,627 @@
+//! Dictionaries distributed in zip archives, e.g. LibreOffice `.oxt`
+//! extensions.
+//!
+//! Extensions declare their dictionaries in a `dictionaries.xcu` file, with
+//! their locales and the location of their `.aff` and `.dic` files.
+
+use std::fs;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::config::SourceConfig;
+use crate::data::{self, InstallInfo, SourceInfo};
+use crate::dirs::Dirs;
+use crate::download;
+use crate::encoding;
+use crate::locale::Locale;
+use crate::SpellError;
+use roxmltree::{Document, Node};
+use zip::ZipArchive;
+
+const OOR_NS: &str = "http://openoffice.org/2001/registry";
+
+/// A spelling dictionary declared in an archive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveDictionary {
+    /// Locales supported by the dictionary, e.g. `fr-FR`.
+    pub locales: Vec<String>,
+    /// Path of the `.aff` file in the archive.
+    pub aff: String,
+    /// Path of the `.dic` file in the archive.
+    pub dic: String,
+}
+
+impl ArchiveDictionary {
+    /// Name of the dictionary, the file name of its `.aff` file without
+    /// extension.
+    pub fn name(&self) -> &str {
+        let file_name = self.aff.rsplit('/').next().unwrap();
+        file_name.strip_suffix(".aff").unwrap_or(file_name)
+    }
+}
+
+fn oor_name<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.attribute((OOR_NS, "name"))
+}
+
+fn prop_values(node: Node, name: &str) -> Vec<String> {
+    node.children()
+        .filter(|child| child.has_tag_name("prop") && oor_name(*child) == Some(name))
+        .flat_map(|prop| prop.children().filter(|value| value.has_tag_name("value")))
+        .flat_map(|value| {
+            value
+                .text()
+                .unwrap_or("")
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Parses the spelling dictionaries of a `dictionaries.xcu` file, `base` is
+/// the directory of the file in the archive, used for `%origin%`.
+fn parse_xcu(xml: &str, base: &str) -> Result<Vec<ArchiveDictionary>, SpellError> {
+    let doc = Document::parse(xml).map_err(SpellError::ParseDocumentError)?;
+    let dictionaries = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("node") && oor_name(*node) == Some("Dictionaries"))
+        .flat_map(|node| node.children().filter(|child| child.has_tag_name("node")));
+    let mut out = Vec::new();
+    for node in dictionaries {
+        if prop_values(node, "Format").first().map(String::as_str) != Some("DICT_SPELL") {
+            continue;
+        }
+        let locations: Vec<String> = prop_values(node, "Locations")
+            .iter()
+            .map(|location| {
+                let path = location.trim_start_matches("%origin%/");
+                format!("{}{}", base, path)
+            })
+            .collect();
+        let aff = locations.iter().find(|l| l.ends_with(".aff"));
+        let dic = locations.iter().find(|l| l.ends_with(".dic"));
+        if let (Some(aff), Some(dic)) = (aff, dic) {
+            out.push(ArchiveDictionary {
+                locales: prop_values(node, "Locales"),
+                aff: aff.clone(),
+                dic: dic.clone(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+fn read_member<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, SpellError> {
+    let mut member = zip.by_name(name).map_err(SpellError::ReadArchiveError)?;
+    let mut raw = Vec::new();
+    member
+        .read_to_end(&mut raw)
+        .map_err(SpellError::ReadInputError)?;
+    Ok(raw)
+}
+
+/// Lists the spelling dictionaries declared in the `.xcu` files of an
+/// archive.
+fn dictionaries<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+) -> Result<Vec<ArchiveDictionary>, SpellError> {
+    let xcus: Vec<String> = zip
+        .file_names()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".xcu"))
+        .map(String::from)
+        .collect();
+    let mut out = Vec::new();
+    for xcu in xcus {
+        let raw = read_member(zip, &xcu)?;
+        let base = match xcu.rfind('/') {
+            Some(slash) => &xcu[..=slash],
+            None => "",
+        };
+        out.extend(parse_xcu(&String::from_utf8_lossy(&raw), base)?);
+    }
+    Ok(out)
+}
+
+/// An archive read in memory, with its origin.
+type Opened = (ZipArchive<Cursor<Vec<u8>>>, SourceInfo);
+
+/// Opens an archive from a local path or an URL and returns it with its
+/// origin.
+///
+/// A downloaded archive is read in memory and removed from the cache
+/// directory. With the origin of a previous download, the download is
+/// conditional and `None` is returned when the archive did not change.
+fn open_changed(
+    location: &str,
+    cache: &Path,
+    previous: Option<&SourceInfo>,
+) -> Result<Option<Opened>, SpellError> {
+    let (raw, origin) = if location.contains("://") {
+        let name = location.rsplit('/').next().unwrap_or("archive.zip");
+        // unique, other processes may download the same archive for other
+        // languages
+        let path = data::partial_path(&cache.join(name), "zip");
+        fs::create_dir_all(cache).map_err(SpellError::FileCachingError)?;
+        let origin = match data::download_changed(location, &path, previous)? {
+            Some(origin) => origin,
+            None => return Ok(None),
+        };
+        let raw = fs::read(&path).map_err(SpellError::ReadInputError);
+        let _ = fs::remove_file(&path);
+        (raw?, origin)
+    } else {
+        let raw = fs::read(location).map_err(SpellError::ReadInputError)?;
+        let origin = SourceInfo {
+            source: location.to_string(),
+            ..SourceInfo::default()
+        };
+        (raw, origin)
+    };
+    let zip = ZipArchive::new(Cursor::new(raw)).map_err(SpellError::ReadArchiveError)?;
+    Ok(Some((zip, origin)))
+}
+
+/// Opens an archive from a local path or an URL and returns it with its
+/// origin, unconditionally.
+fn open(location: &str, cache: &Path) -> Result<Opened, SpellError> {
+    open_changed(location, cache, None)?
+        .ok_or_else(|| SpellError::DicNotFound(PathBuf::from(location)))
+}
+
+/// Installs a dictionary of the archive under the given name, unless it is
+/// already installed and not to be replaced, and records its origin.
+///
+/// The caller holds the lock of the dictionary.
+fn install<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    origin: &SourceInfo,
+    dest: &Path,
+    name: &str,
+    (aff, dic): (&str, &str),
+    replace: bool,
+) -> Result<(), SpellError> {
+    if installed(dest, name) && !replace {
+        return Ok(());
+    }
+    let mut raws = Vec::new();
+    for (member, ext) in &[(aff, "aff"), (dic, "dic")] {
+        let raw = read_member(zip, member)?;
+        download::sanity_check(ext, &raw).map_err(|reason| SpellError::CorruptDicError {
+            url: member.to_string(),
+            reason: reason.to_string(),
+        })?;
+        raws.push(raw);
+    }
+    let encoding = encoding::detect_encoding(&raws[0])?;
+    let mut lossy = false;
+    let mut sources = Vec::new();
+    for ((member, ext), raw) in [(aff, "aff"), (dic, "dic")].iter().zip(&raws) {
+        lossy |= data::install_raw(&dest.join(format!("{}.{}", name, ext)), raw, encoding)?;
+        sources.push(SourceInfo {
+            source: format!("{}#{}", origin.source, member),
+            sha256: download::sha256_hex(raw),
+            ..origin.clone()
+        });
+    }
+    let dic_source = sources.pop().unwrap();
+    let aff_source = sources.pop().unwrap();
+    InstallInfo::new(encoding, lossy, aff_source, dic_source)
+        .write(&InstallInfo::path(dest, name))?;
+    log::info!(
+        "installed dictionary {} from {}#{}",
+        name,
+        origin.source,
+        aff
+    );
+    Ok(())
+}
+
+/// Installs all the dictionaries of an archive in the given directory, the
+/// `selected` one under the given name and the others under their own name.
+///
+/// Only the selected dictionary is replaced when `replace` is set, the
+/// caller holds its lock. Other dictionaries with the name of the selected
+/// one are skipped.
+fn install_all<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    origin: &SourceInfo,
+    dest: &Path,
+    selected: Option<(&str, usize)>,
+    replace: bool,
+) -> Result<Vec<String>, SpellError> {
+    let mut names = Vec::new();
+    for (i, dict) in dictionaries(zip)?.iter().enumerate() {
+        let (name, replace, _lock) = match selected {
+            Some((name, index)) if index == i => (name, replace, None),
+            // locking it again would wait for the caller forever
+            Some((name, _)) if name == dict.name() => continue,
+            _ => {
+                let name = dict.name();
+                (name, false, Some(data::lock_dictionary(dest, name)?))
+            }
+        };
+        install(zip, origin, dest, name, (&dict.aff, &dict.dic), replace)?;
+        names.push(name.to_string());
+    }
+    Ok(names)
+}
+
+/// Lists the spelling dictionaries declared in an archive, from a local path
+/// or an URL.
+pub fn archive_dictionaries(location: &str) -> Result<Vec<ArchiveDictionary>, SpellError> {
+    dictionaries(&mut open(location, &Dirs::from_env().cache)?.0)
+}
+
+/// Installs all the dictionaries declared in an archive, from a local path
+/// or an URL, and returns their names.
+///
+/// Dictionaries already installed are kept as is.
+pub fn install_archive(location: &str) -> Result<Vec<String>, SpellError> {
+    let dirs = Dirs::from_env();
+    let (mut zip, origin) = open(location, &dirs.cache)?;
+    install_all(&mut zip, &origin, &dirs.dictionaries(), None, false)
+}
+
+/// Installs the dictionary of a language from an archive source.
+///
+/// Without explicit member paths, the dictionary supporting the language is
+/// found in `dictionaries.xcu` and the other dictionaries of the archive are
+/// installed too. Returns whether it was installed by this call.
+pub(crate) fn install_source(
+    lang: &str,
+    archive: &str,
+    source: &SourceConfig,
+    dirs: &Dirs,
+) -> Result<bool, SpellError> {
+    let dest = dirs.dictionaries();
+    // the lock comes first, the archive is downloaded once
+    let _lock = data::lock_dictionary(&dest, lang)?;
+    if installed(&dest, lang) {
+        return Ok(false);
+    }
+    let (mut zip, origin) = open(archive, &dirs.cache)?;
+    install_source_from(&mut zip, lang, &origin, source, &dest, false)?;
+    Ok(true)
+}
+
+fn installed(dest: &Path, name: &str) -> bool {
+    ["aff", "dic"]
+        .iter()
+        .all(|ext| dest.join(format!("{}.{}", name, ext)).exists())
+}
+
+/// Verifies the members of a source against its expected checksums, if any.
+fn verify<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    archive: &str,
+    (aff, dic): (&str, &str),
+    source: &SourceConfig,
+) -> Result<(), SpellError> {
+    for (member, expected) in &[(aff, &source.aff_sha256), (dic, &source.dic_sha256)] {
+        if let Some(expected) = expected {
+            let actual = download::sha256_hex(&read_member(zip, member)?);
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                return Err(SpellError::ChecksumError {
+                    url: format!("{}#{}", archive, member),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn install_source_from<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    lang: &str,
+    origin: &SourceInfo,
+    source: &SourceConfig,
+    dest: &Path,
+    replace: bool,
+) -> Result<(), SpellError> {
+    let archive = origin.source.as_str();
+    if !source.aff.is_empty() && !source.dic.is_empty() {
+        let members = (source.aff.as_str(), source.dic.as_str());
+        verify(zip, archive, members, source)?;
+        return install(zip, origin, dest, lang, members, replace);
+    }
+    let dicts = dictionaries(zip)?;
+    let locales: Vec<String> = dicts.iter().flat_map(|d| d.locales.clone()).collect();
+    let index = Locale::parse(lang)
+        .and_then(|locale| locale.choose(&locales).map(String::from))
+        .and_then(|locale| dicts.iter().position(|d| d.locales.contains(&locale)))
+        .ok_or_else(|| SpellError::DicNotFound(PathBuf::from(archive)))?;
+    verify(zip, archive, (&dicts[index].aff, &dicts[index].dic), source)?;
+    install_all(zip, origin, dest, Some((lang, index)), replace)?;
+    Ok(())
+}
+
+/// Installs the dictionary of a language from an archive source again if
+/// its members changed since the previous install, returns whether it
+/// changed.
+///
+/// Like other sources, the archive is only downloaded again when the server
+/// tells it changed since the previous install.
+pub(crate) fn update_source(
+    lang: &str,
+    archive: &str,
+    source: &SourceConfig,
+    dirs: &Dirs,
+    previous: Option<&InstallInfo>,
+) -> Result<bool, SpellError> {
+    let dest = dirs.dictionaries();
+    let _lock = data::lock_dictionary(&dest, lang)?;
+    let downloaded = previous.map(|info| &info.aff).filter(|info| {
+        installed(&dest, lang)
+            && info
+                .source
+                .rsplit_once('#')
+                .is_some_and(|(location, _)| location == archive)
+    });
+    let (mut zip, origin) = match open_changed(archive, &dirs.cache, downloaded)? {
+        Some(opened) => opened,
+        None => return Ok(false),
+    };
+    update_source_from(&mut zip, lang, &origin, source, &dest, previous)
+}
+
+fn update_source_from<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    lang: &str,
+    origin: &SourceInfo,
+    source: &SourceConfig,
+    dest: &Path,
+    previous: Option<&InstallInfo>,
+) -> Result<bool, SpellError> {
+    if let Some(previous) = previous {
+        let unchanged = installed(dest, lang)
+            && [&previous.aff, &previous.dic].iter().all(|info| {
+                match info.source.rsplit_once('#') {
+                    Some((_, member)) => read_member(zip, member)
+                        .map(|raw| download::sha256_hex(&raw) == info.sha256)
+                        .unwrap_or(false),
+                    None => false,
+                }
+            });
+        if unchanged {
+            // the validators of the new download spare the next one
+            let mut info = previous.clone();
+            for file in [&mut info.aff, &mut info.dic] {
+                file.etag = origin.etag.clone();
+                file.last_modified = origin.last_modified.clone();
+            }
+            if info != *previous {
+                info.write(&InstallInfo::path(dest, lang))?;
+            }
+            return Ok(false);
+        }
+    }
+    install_source_from(zip, lang, origin, source, dest, true)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{serve_headers, zip, TempDir};
+
+    const XCU: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<oor:component-data xmlns:oor="http://openoffice.org/2001/registry" oor:name="Linguistic" oor:package="org.openoffice.Office">
+  <node oor:name="ServiceManager">
+    <node oor:name="Dictionaries">
+      <node oor:name="HunSpellDic_fr" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list">
+          <value>%origin%/fr.aff %origin%/fr.dic</value>
+        </prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_SPELL</value></prop>
+        <prop oor:name="Locales" oor:type="oor:string-list"><value>fr-FR fr-BE fr-CA</value></prop>
+      </node>
+      <node oor:name="HyphDic_fr" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list"><value>%origin%/hyph_fr.dic</value></prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_HYPH</value></prop>
+        <prop oor:name="Locales" oor:type="oor:string-list"><value>fr-FR</value></prop>
+      </node>
+      <node oor:name="HunSpellDic_br" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list">
+          <value>%origin%/br_FR.aff %origin%/br_FR.dic</value>
+        </prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_SPELL</value></prop>
+        <prop oor:name="Locales" oor:type="oor:string-list"><value>br-FR</value></prop>
+      </node>
+    </node>
+  </node>
+</oor:component-data>"#;
+
+    fn oxt() -> ZipArchive<Cursor<Vec<u8>>> {
+        let raw = zip(&[
+            ("META-INF/manifest.xml", "<manifest/>"),
+            ("dicts/dictionaries.xcu", XCU),
+            ("dicts/fr.aff", "SET UTF-8\nTRY esaitnr\n"),
+            ("dicts/fr.dic", "2\nchat\nchien\n"),
+            ("dicts/br_FR.aff", "SET UTF-8\nTRY aenr\n"),
+            ("dicts/br_FR.dic", "1\nkazh\n"),
+        ]);
+        ZipArchive::new(Cursor::new(raw)).unwrap()
+    }
+
+    #[test]
+    fn xcu() {
+        let dicts = dictionaries(&mut oxt()).unwrap();
+        assert_eq!(
+            dicts,
+            vec![
+                ArchiveDictionary {
+                    locales: vec![
+                        String::from("fr-FR"),
+                        String::from("fr-BE"),
+                        String::from("fr-CA")
+                    ],
+                    aff: String::from("dicts/fr.aff"),
+                    dic: String::from("dicts/fr.dic"),
+                },
+                ArchiveDictionary {
+                    locales: vec![String::from("br-FR")],
+                    aff: String::from("dicts/br_FR.aff"),
+                    dic: String::from("dicts/br_FR.dic"),
+                },
+            ]
+        );
+        assert_eq!(dicts[0].name(), "fr");
+    }
+
+    #[test]
+    fn install_from_xcu() {
+        let dir = TempDir::new("archive-install");
+        let dest = dir.path().to_owned();
+        let source = SourceConfig {
+            archive: Some(String::from("fr.oxt")),
+            ..SourceConfig::default()
+        };
+        let origin = SourceInfo {
+            source: String::from("fr.oxt"),
+            ..SourceInfo::default()
+        };
+        install_source_from(&mut oxt(), "fr_CA", &origin, &source, &dest, false).unwrap();
+        let mut installed: Vec<String> = fs::read_dir(&dest)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        installed.sort();
+        assert_eq!(
+            installed,
+            vec![
+                "br_FR.aff",
+                "br_FR.dic",
+                "br_FR.lock",
+                "br_FR.meta.toml",
+                "fr_CA.aff",
+                "fr_CA.dic",
+                "fr_CA.meta.toml"
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("fr_CA.dic")).unwrap(),
+            "2\nchat\nchien\n"
+        );
+        let info = InstallInfo::read(&InstallInfo::path(&dest, "fr_CA")).unwrap();
+        assert_eq!(info.dic.source, "fr.oxt#dicts/fr.dic");
+        match install_source_from(&mut oxt(), "de_DE", &origin, &source, &dest, false) {
+            Err(SpellError::DicNotFound(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let update = |info: &InstallInfo| {
+            update_source_from(&mut oxt(), "fr_CA", &origin, &source, &dest, Some(info)).unwrap()
+        };
+        assert!(!update(&info));
+        let mut outdated = info.clone();
+        outdated.aff.sha256 = String::from("00");
+        fs::write(dest.join("fr_CA.dic"), "1\nchat\n").unwrap();
+        assert!(update(&outdated));
+        assert_eq!(
+            fs::read_to_string(dest.join("fr_CA.dic")).unwrap(),
+            "2\nchat\nchien\n"
+        );
+        // the validators of an unchanged archive downloaded again are kept
+        let downloaded = SourceInfo {
+            etag: Some(String::from("\"v2\"")),
+            ..origin.clone()
+        };
+        let info = InstallInfo::read(&InstallInfo::path(&dest, "fr_CA")).unwrap();
+        assert!(!update_source_from(
+            &mut oxt(),
+            "fr_CA",
+            &downloaded,
+            &source,
+            &dest,
+            Some(&info)
+        )
+        .unwrap());
+        let info = InstallInfo::read(&InstallInfo::path(&dest, "fr_CA")).unwrap();
+        assert_eq!(info.aff.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(info.dic.source, "fr.oxt#dicts/fr.dic");
+
+        let pinned = |aff_sha256: &str| SourceConfig {
+            archive: Some(String::from("fr.oxt")),
+            aff_sha256: Some(String::from(aff_sha256)),
+            dic_sha256: Some(download::sha256_hex(b"2\nchat\nchien\n")),
+            ..SourceConfig::default()
+        };
+        let install_pinned = |source: &SourceConfig| {
+            install_source_from(&mut oxt(), "fr", &origin, source, &dest, true)
+        };
+        match install_pinned(&pinned("00")) {
+            Err(SpellError::ChecksumError { url, .. }) => assert_eq!(url, "fr.oxt#dicts/fr.aff"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!dest.join("fr.aff").exists());
+        install_pinned(&pinned(&download::sha256_hex(b"SET UTF-8\nTRY esaitnr\n"))).unwrap();
+        assert!(dest.join("fr.aff").exists());
+    }
+
+    #[test]
+    fn same_locale() {
+        let node = |name: &str| {
+            format!(
+                r#"<node oor:name="{0}"><prop oor:name="Locations"><value>%origin%/{0}.aff %origin%/{0}.dic</value></prop><prop oor:name="Format"><value>DICT_SPELL</value></prop><prop oor:name="Locales"><value>de-DE</value></prop></node>"#,
+                name
+            )
+        };
+        let xcu = format!(
+            r#"<oor:component-data xmlns:oor="http://openoffice.org/2001/registry"><node oor:name="Dictionaries">{}{}</node></oor:component-data>"#,
+            node("de_DE_frami"),
+            node("de_DE")
+        );
+        let base = TempDir::new("archive-same-locale");
+        let dirs = Dirs::in_data_dir(base.join("data"));
+        let archive = base.join("de.oxt");
+        let raw = zip(&[
+            ("dictionaries.xcu", &xcu),
+            ("de_DE_frami.aff", "SET UTF-8\n"),
+            ("de_DE_frami.dic", "1\nStraße\n"),
+            ("de_DE.aff", "SET UTF-8\n"),
+            ("de_DE.dic", "1\nStrasse\n"),
+        ]);
+        fs::write(&archive, raw).unwrap();
+        // the first dictionary of the locale is installed, under the name of
+        // the language, with the lock of the language held
+        let location = archive.to_str().unwrap();
+        let source = SourceConfig {
+            archive: Some(location.to_string()),
+            ..SourceConfig::default()
+        };
+        assert!(install_source("de_DE", location, &source, &dirs).unwrap());
+        let dest = dirs.dictionaries();
+        assert_eq!(
+            fs::read_to_string(dest.join("de_DE.dic")).unwrap(),
+            "1\nStraße\n"
+        );
+        let info = InstallInfo::read(&InstallInfo::path(&dest, "de_DE")).unwrap();
+        assert!(info.dic.source.ends_with("de.oxt#de_DE_frami.dic"));
+    }
+
+    #[test]
+    fn archive_update() {
+        let oxt = zip(&[("fr.aff", "SET UTF-8\n"), ("fr.dic", "1\nchat\n")]);
+        let oxt: &'static [u8] = Box::leak(oxt.into_boxed_slice());
+        let (url, requests) = serve_headers(vec![(200, "ETag: \"v1\"\r\n", oxt), (304, "", b"")]);
+        let base = TempDir::new("archive-update");
+        let config: crate::Config = toml::from_str(&format!(
+            "[dictionaries.sources.fr]\narchive = \"{}/fr.oxt\"\naff = \"fr.aff\"\ndic = \"fr.dic\"\n",
+            url
+        ))
+        .unwrap();
+        let dirs = Dirs::in_data_dir(base.join("data"));
+        let provider = data::LangProvider::in_dirs("fr", &config, dirs.clone());
+        provider.ensure_data().unwrap();
+        let info = provider.install_info().unwrap();
+        assert_eq!(info.dic.source, format!("{}/fr.oxt#fr.dic", url));
+        assert_eq!(info.aff.etag.as_deref(), Some("\"v1\""));
+        assert!(!provider.update().unwrap());
+        assert!(requests.lock().unwrap()[1].contains("If-None-Match: \"v1\"\r\n"));
+        // the archive does not stay in the cache
+        assert_eq!(fs::read_dir(&dirs.cache).unwrap().count(), 0);
+    }
+}
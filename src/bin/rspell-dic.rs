@@ -1,4 +1,6 @@
-use rspell::{LangProvider, SpellError};
+use std::path::PathBuf;
+
+use rspell::{Config, DictionaryInfo, LangProvider, Severity, SpellError};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -9,18 +11,125 @@ enum Cli {
     Ensure { lang: String },
     /// Removes the files for the given language
     Remove { lang: String },
+    /// Updates the files for the given language if their sources changed
+    Update {
+        /// Language to update
+        #[structopt(required_unless = "all")]
+        lang: Option<String>,
+        /// Updates all the installed dictionaries
+        #[structopt(long, conflicts_with = "lang")]
+        all: bool,
+    },
+    /// Installs the dictionaries of a zip archive or a LibreOffice extension
+    Install {
+        /// Path or URL of the archive
+        archive: String,
+    },
+    /// Exports dictionaries to a bundle, to import them on an offline machine
+    Export {
+        /// Languages to export, installed first if needed
+        #[structopt(required = true)]
+        langs: Vec<String>,
+        /// Location of the bundle
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Installs the dictionaries of a bundle made by export
+    Import {
+        /// Location of the bundle
+        #[structopt(parse(from_os_str))]
+        bundle: PathBuf,
+    },
+    /// Removes stale cache files and incomplete or unused dictionaries
+    Gc {
+        /// Also removes the dictionaries not used for this many days
+        #[structopt(long)]
+        unused_days: Option<u64>,
+        /// Only reports the files to remove
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Manages the configuration
+    Config(ConfigCommand),
+    /// Lists the dictionaries, all kinds when no filter is given
+    List {
+        /// Dictionaries installed in the rspell data directory
+        #[structopt(long)]
+        installed: bool,
+        /// Dictionaries found in the configured directories
+        #[structopt(long)]
+        system: bool,
+        /// Dictionaries available from the configured sources
+        #[structopt(long)]
+        remote: bool,
+    },
+}
+
+#[derive(StructOpt)]
+enum ConfigCommand {
+    /// Checks a config file, the user one by default
+    Check {
+        /// Location of the config file
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+}
+
+fn print_dictionaries(kind: &str, dicts: &[DictionaryInfo]) {
+    for dict in dicts {
+        println!(
+            "{:<9} {:<14} {:<10} {:<8} {:>9}  {}",
+            kind,
+            dict.name,
+            dict.encoding,
+            dict.lang.as_deref().unwrap_or("-"),
+            format!("{} KiB", dict.size / 1024),
+            dict.dic.display()
+        );
+    }
+}
+
+/// Prints the issues of a config file, exiting with an error status when it
+/// has errors or cannot be loaded at all.
+fn check_config(file: Option<PathBuf>) -> Result<(), SpellError> {
+    let file = file.unwrap_or_else(rspell::config_file);
+    let raw = std::fs::read_to_string(&file).map_err(SpellError::ReadConfigError)?;
+    let issues = match Config::validate(&raw) {
+        Ok(issues) => issues,
+        Err(SpellError::LoadConfigError(err)) => {
+            println!("{}: error: {}", file.display(), err);
+            std::process::exit(1);
+        }
+        Err(err) => return Err(err),
+    };
+    for issue in &issues {
+        println!("{}:{}", file.display(), issue);
+    }
+    if issues.iter().any(|issue| issue.severity == Severity::Error) {
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), SpellError> {
     env_logger::init();
+    // the config to check may not load, and checking it must not create it
+    let cli = match Cli::from_args() {
+        Cli::Config(ConfigCommand::Check { file }) => return check_config(file),
+        cli => cli,
+    };
     let config = rspell::load_config()?;
-    match Cli::from_args() {
+    match cli {
         Cli::Info { lang } => {
             let provider = LangProvider::new(&lang, &config);
-            let source = config.dictionaries.sources.get(&lang);
+            let source = config.dictionaries.sources.get(provider.lang());
             println!("language code     : {}", lang);
+            println!("dictionary        : {}", provider.lang());
             println!("aff file exists   : {}", provider.aff().exists());
             println!("dic file exists   : {}", provider.dic().exists());
+            if let Some(archive) = source.and_then(|s| s.archive.as_deref()) {
+                println!("archive url       : {}", archive);
+            }
             println!(
                 "aff file url      : {}",
                 match source {
@@ -36,25 +145,39 @@ fn main() -> Result<(), SpellError> {
                 }
             );
             let aff_on_disk = provider.aff_on_disk();
-            if aff_on_disk.len() > 0 {
+            if !aff_on_disk.is_empty() {
                 println!(
                     "aff files on disk : {}",
-                    aff_on_disk.get(0).unwrap().display()
+                    aff_on_disk.first().unwrap().display()
                 );
                 for d in aff_on_disk.iter().skip(1) {
                     println!("                  : {}", d.display());
                 }
             }
             let dic_on_disk = provider.dic_on_disk();
-            if dic_on_disk.len() > 0 {
+            if !dic_on_disk.is_empty() {
                 println!(
                     "dic files on disk : {}",
-                    dic_on_disk.get(0).unwrap().display()
+                    dic_on_disk.first().unwrap().display()
                 );
                 for d in dic_on_disk.iter().skip(1) {
                     println!("                  : {}", d.display());
                 }
             }
+            if provider.aff().exists() && provider.dic().exists() {
+                let provenance = provider.provenance()?;
+                println!("encoding          : {}", provenance.encoding);
+                if let Some(encoding) = &provenance.source_encoding {
+                    println!("source encoding   : {}", encoding);
+                }
+                for (ext, file) in &[("aff", &provenance.aff), ("dic", &provenance.dic)] {
+                    println!("{} file origin   : {}", ext, file.origin);
+                    println!("{} file sha256   : {}", ext, file.sha256);
+                    if let Some(sha256) = &file.source_sha256 {
+                        println!("{} source sha256 : {}", ext, sha256);
+                    }
+                }
+            }
             Ok(())
         }
         Cli::Ensure { lang } => {
@@ -65,5 +188,98 @@ fn main() -> Result<(), SpellError> {
             let provider = LangProvider::new(&lang, &config);
             provider.remove_data()
         }
+        Cli::Update { lang, all } => {
+            let langs = if all {
+                rspell::installed_dictionaries()
+                    .into_iter()
+                    .map(|dict| dict.name)
+                    .collect()
+            } else {
+                lang.into_iter().collect::<Vec<_>>()
+            };
+            for lang in langs {
+                let provider = LangProvider::new(&lang, &config);
+                if provider.update()? {
+                    println!("updated {}", provider.lang());
+                } else {
+                    println!("{} is up to date", provider.lang());
+                }
+            }
+            Ok(())
+        }
+        Cli::Install { archive } => {
+            for name in rspell::install_archive(&archive)? {
+                println!("installed {}", name);
+            }
+            Ok(())
+        }
+        Cli::Export { langs, output } => {
+            let providers: Vec<_> = langs
+                .iter()
+                .map(|lang| LangProvider::new(lang, &config))
+                .collect();
+            for provider in &providers {
+                provider.ensure_data()?;
+            }
+            rspell::export_bundle(&providers, &output)?;
+            let names: Vec<&str> = providers.iter().map(LangProvider::lang).collect();
+            println!("exported {} to {}", names.join(", "), output.display());
+            Ok(())
+        }
+        Cli::Import { bundle } => {
+            for name in rspell::import_bundle(&bundle)? {
+                println!("imported {}", name);
+            }
+            Ok(())
+        }
+        Cli::Gc {
+            unused_days,
+            dry_run,
+        } => {
+            let garbage = rspell::collect_garbage(unused_days, dry_run)?;
+            for file in &garbage {
+                println!(
+                    "{:<9} {:>9}  {}",
+                    file.kind,
+                    format!("{} KiB", file.size / 1024),
+                    file.path.display()
+                );
+            }
+            let size: u64 = garbage.iter().map(|file| file.size).sum();
+            println!(
+                "{} {} files, {} KiB",
+                if dry_run { "would remove" } else { "removed" },
+                garbage.len(),
+                size / 1024
+            );
+            Ok(())
+        }
+        Cli::Config(_) => unreachable!("handled before loading the config"),
+        Cli::List {
+            installed,
+            system,
+            remote,
+        } => {
+            let all = !(installed || system || remote);
+            if all || installed {
+                print_dictionaries("installed", &rspell::installed_dictionaries());
+            }
+            if all || system {
+                print_dictionaries("system", &rspell::system_dictionaries(&config));
+            }
+            if all || remote {
+                for dict in rspell::remote_dictionaries(&config) {
+                    let location = match &dict.archive {
+                        Some(archive) if dict.dic_url.is_empty() => archive.clone(),
+                        Some(archive) => {
+                            format!("{} ({}, {})", archive, dict.aff_url, dict.dic_url)
+                        }
+                        None => dict.dic_url,
+                    };
+                    println!("{:<9} {:<14} {}", "remote", dict.name, location);
+                }
+            }
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,210 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use rspell::modes::email::{self, Location};
+use rspell::modes::{notebook, office, Mode, Position};
+use rspell::{BadWord, Config, Spell, SpellError};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+/// Checks the spelling of documents
+struct Cli {
+    /// Language codes of the dictionaries to use, separated by commas, taken
+    /// from the project `.rspell.toml` or en_US by default
+    #[structopt(short, long)]
+    lang: Option<String>,
+    /// Input mode (text, rst, asciidoc, markdown, commit or a programming
+    /// language), guessed from the file name by default
+    #[structopt(short, long)]
+    mode: Option<Mode>,
+    /// Detect the language of each paragraph among the given ones, and check
+    /// it with its dictionary only
+    #[structopt(short, long)]
+    detect: bool,
+    /// Files to check, the standard input is read when absent
+    ///
+    /// Office documents (.docx, .odt) are checked with the dictionary of the
+    /// language of each part of their text. Jupyter notebooks (.ipynb) are
+    /// checked cell by cell. Messages (.eml) and mailboxes (.mbox) are checked
+    /// message by message.
+    ///
+    /// The closest `.rspell.toml` and `.rspell.dic` project files found from
+    /// the directory of each file are merged over the user configuration.
+    files: Vec<PathBuf>,
+}
+
+/// Spellchecker and configuration of a project.
+struct Project {
+    config: Config,
+    spell: Spell,
+}
+
+impl Project {
+    fn load(cli: &Cli, path: &Path) -> Result<Project, SpellError> {
+        let config = rspell::load_project_config(path)?;
+        let lang = cli
+            .lang
+            .clone()
+            .or_else(|| config.lang.clone())
+            .unwrap_or_else(|| String::from("en_US"));
+        let langs: Vec<&str> = lang.split(',').map(str::trim).collect();
+        let spell = Spell::with_config_multi(&langs, &config)?;
+        Ok(Project { config, spell })
+    }
+}
+
+fn read_input(path: Option<&PathBuf>) -> Result<String, SpellError> {
+    match path {
+        Some(path) => fs::read_to_string(path).map_err(SpellError::ReadInputError),
+        None => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(SpellError::ReadInputError)?;
+            Ok(text)
+        }
+    }
+}
+
+fn check(project: &Project, cli: &Cli, path: Option<&PathBuf>) -> Result<usize, SpellError> {
+    let text = read_input(path)?;
+    let mode = cli
+        .mode
+        .or_else(|| path.and_then(Mode::from_path))
+        .unwrap_or(Mode::Text);
+    let name = path.map_or(String::from("<stdin>"), |p| p.display().to_string());
+    let bad_words: Vec<(Option<String>, BadWord)> = if cli.detect {
+        project
+            .spell
+            .check_detected(mode, &text)?
+            .into_iter()
+            .map(|bad| (Some(bad.lang), bad.bad))
+            .collect()
+    } else {
+        project
+            .spell
+            .check_document(mode, &text)
+            .into_iter()
+            .map(|bad| (None, bad))
+            .collect()
+    };
+    for (lang, bad) in &bad_words {
+        let pos = Position::of(&text, bad.offset);
+        let lang = lang.as_ref().map_or(String::new(), |l| format!(" [{}]", l));
+        println!(
+            "{}:{}:{}: {}{} ({})",
+            name,
+            pos.line,
+            pos.column,
+            bad.word,
+            lang,
+            bad.suggestions.join(", ")
+        );
+    }
+    Ok(bad_words.len())
+}
+
+fn check_office(project: &Project, path: &Path) -> Result<usize, SpellError> {
+    let paragraphs = office::read_paragraphs(path)?;
+    let mut spells = HashMap::new();
+    let langs = project.spell.langs();
+    for lang in office::languages(&paragraphs) {
+        if !langs.contains(&lang) {
+            match Spell::with_config(lang, &project.config) {
+                Ok(other) => {
+                    spells.insert(lang, other);
+                }
+                Err(err) => log::warn!("skipping text in {}: {:?}", lang, err),
+            }
+        }
+    }
+    let bad_words = office::check(&paragraphs, |lang| match lang {
+        Some(lang) if !langs.contains(&lang) => spells.get(lang),
+        _ => Some(&project.spell),
+    });
+    for bad in &bad_words {
+        println!(
+            "{}:paragraph {}: {} \"{}\" ({})",
+            path.display(),
+            bad.paragraph,
+            bad.bad.word,
+            bad.excerpt,
+            bad.bad.suggestions.join(", ")
+        );
+    }
+    Ok(bad_words.len())
+}
+
+fn check_notebook(spell: &Spell, path: &Path) -> Result<usize, SpellError> {
+    let text = fs::read_to_string(path).map_err(SpellError::ReadInputError)?;
+    let cells = notebook::read_cells(&text)?;
+    let bad_words = notebook::check(spell, &cells);
+    for bad in &bad_words {
+        println!(
+            "{}:cell {}:{}:{}: {} ({})",
+            path.display(),
+            bad.cell,
+            bad.position.line,
+            bad.position.column,
+            bad.bad.word,
+            bad.bad.suggestions.join(", ")
+        );
+    }
+    Ok(bad_words.len())
+}
+
+fn check_email(spell: &Spell, path: &Path) -> Result<usize, SpellError> {
+    let raw = fs::read(path).map_err(SpellError::ReadInputError)?;
+    let messages = email::read_messages(&raw);
+    let bad_words = email::check(spell, &messages);
+    for bad in &bad_words {
+        let location = match bad.location {
+            Location::Subject => String::from("subject"),
+            Location::Body { part, position } => {
+                format!("body {}:{}:{}", part, position.line, position.column)
+            }
+        };
+        println!(
+            "{}:message {}:{}: {} ({})",
+            path.display(),
+            bad.message,
+            location,
+            bad.bad.word,
+            bad.bad.suggestions.join(", ")
+        );
+    }
+    Ok(bad_words.len())
+}
+
+fn main() -> Result<(), SpellError> {
+    env_logger::init();
+    let cli = Cli::from_args();
+    let mut count = 0;
+    if cli.files.is_empty() {
+        let project = Project::load(&cli, Path::new("."))?;
+        count += check(&project, &cli, None)?;
+    }
+    let mut projects = HashMap::new();
+    for path in &cli.files {
+        let project = match projects.entry(rspell::find_project(path)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Project::load(&cli, path)?),
+        };
+        count += if office::is_office(path) {
+            check_office(project, path)?
+        } else if notebook::is_notebook(path) {
+            check_notebook(&project.spell, path)?
+        } else if email::is_email(path) {
+            check_email(&project.spell, path)?
+        } else {
+            check(project, &cli, Some(path))?
+        };
+    }
+    if count > 0 {
+        eprintln!("{} misspelt word(s) found", count);
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use crate::config::{self, Config};
+use crate::dirs::Dirs;
+use crate::{Spell, SpellError};
+
+/// Builder of a [Spell](struct.Spell.html), see
+/// [Spell::builder()](struct.Spell.html#method.builder).
+///
+/// Nothing is read from the user directories when both the configuration and
+/// the data directory are given, except the `user_directories` of the
+/// configuration, relative to the home directory.
+#[derive(Default)]
+pub struct SpellBuilder {
+    langs: Vec<String>,
+    config: Option<Config>,
+    data_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl SpellBuilder {
+    /// Adds a language, the first one is the primary language.
+    ///
+    /// The languages of the configuration are used when none is given.
+    pub fn lang(mut self, lang: &str) -> Self {
+        self.langs.push(lang.to_string());
+        self
+    }
+
+    /// Sets the configuration, the user one is loaded otherwise.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Sets the directory of the installed and personal dictionaries.
+    ///
+    /// The cache directory defaults to a `cache` directory in it.
+    pub fn data_dir<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.data_dir = Some(path.into());
+        self
+    }
+
+    /// Sets the directory of the downloaded files.
+    pub fn cache_dir<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.cache_dir = Some(path.into());
+        self
+    }
+
+    /// Creates the spellchecker, ensuring that the data files of its
+    /// languages are available.
+    ///
+    /// Fails with `NoLanguage` if no language is given, neither by the builder
+    /// nor by the configuration.
+    pub fn build(self) -> Result<Spell, SpellError> {
+        let config = match self.config {
+            Some(config) => config,
+            None => config::load_config()?,
+        };
+        let mut dirs = match self.data_dir {
+            Some(data) => Dirs::in_data_dir(data),
+            None => Dirs::from_env(),
+        };
+        if let Some(cache) = self.cache_dir {
+            dirs.cache = cache;
+        }
+        let langs = if self.langs.is_empty() {
+            config
+                .lang
+                .iter()
+                .flat_map(|lang| lang.split(','))
+                .map(str::trim)
+                .filter(|lang| !lang.is_empty())
+                .map(String::from)
+                .collect()
+        } else {
+            self.langs
+        };
+        let langs: Vec<&str> = langs.iter().map(String::as_str).collect();
+        Spell::with_dirs(&langs, &config, &dirs)
+    }
+}
@@ -0,0 +1,187 @@
+//! Bundles of installed dictionaries, to install them on machines without
+//! network access.
+//!
+//! A bundle is a tar archive of the `.aff` and `.dic` files in use, converted
+//! ones of the data directory or UTF-8 ones of the system used in place, with
+//! their install metadata.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::data::{self, LangProvider};
+use crate::download;
+use crate::SpellError;
+use tar::{Archive, Builder};
+
+/// Extensions of the files of a dictionary in a bundle.
+const EXTENSIONS: &[&str] = &["aff", "dic", "meta.toml"];
+
+fn export_from<W: Write>(providers: &[LangProvider<'_>], writer: W) -> Result<W, SpellError> {
+    let mut builder = Builder::new(writer);
+    for provider in providers {
+        let name = provider.lang();
+        for path in &[provider.aff(), provider.dic()] {
+            if !path.exists() {
+                return Err(SpellError::DicNotFound(path.to_path_buf()));
+            }
+        }
+        let mut files = vec![
+            (provider.aff().to_path_buf(), "aff"),
+            (provider.dic().to_path_buf(), "dic"),
+        ];
+        let info = provider.info_path();
+        // the metadata of the data directory is not the one of the system files
+        if !provider.in_place() && info.exists() {
+            files.push((info, "meta.toml"));
+        }
+        for (path, ext) in &files {
+            builder
+                .append_path_with_name(path, format!("{}.{}", name, ext))
+                .map_err(SpellError::BundleError)?;
+        }
+        log::debug!("exported dictionary {}", name);
+    }
+    builder.into_inner().map_err(SpellError::BundleError)
+}
+
+fn import_into<R: Read>(dir: &Path, reader: R) -> Result<Vec<String>, SpellError> {
+    fs::create_dir_all(dir).map_err(SpellError::FileCachingError)?;
+    let mut archive = Archive::new(reader);
+    let mut names = Vec::new();
+    for entry in archive.entries().map_err(SpellError::BundleError)? {
+        let mut entry = entry.map_err(SpellError::BundleError)?;
+        let path = entry.path().map_err(SpellError::BundleError)?.into_owned();
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            // only the files at the root of the bundle, never outside of the
+            // data directory
+            Some(name)
+                if path.components().count() == 1 && entry.header().entry_type().is_file() =>
+            {
+                name.to_string()
+            }
+            _ => {
+                log::warn!("skipping bundle entry {}", path.display());
+                continue;
+            }
+        };
+        let ext = match EXTENSIONS
+            .iter()
+            .find(|ext| file_name.ends_with(&format!(".{}", ext)))
+        {
+            Some(ext) => *ext,
+            None => {
+                log::warn!("skipping bundle entry {}", file_name);
+                continue;
+            }
+        };
+        let mut raw = Vec::new();
+        entry
+            .read_to_end(&mut raw)
+            .map_err(SpellError::BundleError)?;
+        download::sanity_check(ext, &raw).map_err(|reason| SpellError::CorruptDicError {
+            url: file_name.clone(),
+            reason: reason.to_string(),
+        })?;
+        let name = &file_name[..file_name.len() - ext.len() - 1];
+        let _lock = data::lock_dictionary(dir, name)?;
+        data::write_atomic(&dir.join(&file_name), &raw).map_err(SpellError::FileCachingError)?;
+        if ext == "aff" {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Exports the dictionaries of the given providers, with their install
+/// metadata, to a bundle.
+///
+/// The files are the ones the providers use, see
+/// [LangProvider::aff()](struct.LangProvider.html#method.aff), so system
+/// dictionaries used in place are exported too. They must be available, see
+/// [LangProvider::ensure_data()](struct.LangProvider.html#method.ensure_data).
+pub fn export_bundle<P: AsRef<Path>>(
+    providers: &[LangProvider<'_>],
+    bundle: P,
+) -> Result<(), SpellError> {
+    let file = File::create(bundle).map_err(SpellError::BundleError)?;
+    let file = export_from(providers, file)?;
+    file.sync_all().map_err(SpellError::BundleError)
+}
+
+/// Installs the dictionaries of a bundle in the data directory and returns
+/// their names.
+///
+/// Installed dictionaries of the same name are replaced, nothing is
+/// downloaded.
+pub fn import_bundle<P: AsRef<Path>>(bundle: P) -> Result<Vec<String>, SpellError> {
+    let file = File::open(bundle).map_err(SpellError::BundleError)?;
+    import_into(&data::dictionaries_dir(), file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::InstallInfo;
+    use crate::dirs::Dirs;
+    use crate::testing::TempDir;
+    use crate::Config;
+    use tar::Header;
+
+    #[test]
+    fn export_import() {
+        let base = TempDir::new("bundle");
+        let (system, dst) = (base.join("system"), base.join("dst"));
+        let dirs = Dirs::in_data_dir(base.join("data"));
+        let src = dirs.dictionaries();
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&system).unwrap();
+        fs::copy("files/fr.aff", src.join("fr.aff")).unwrap();
+        fs::copy("files/fr.dic", src.join("fr.dic")).unwrap();
+        let info = InstallInfo::default();
+        info.write(&InstallInfo::path(&src, "fr")).unwrap();
+        fs::copy("files/en_US.aff", system.join("en_US.aff")).unwrap();
+        fs::copy("files/en_US.dic", system.join("en_US.dic")).unwrap();
+        let config: Config = toml::from_str(&format!(
+            "[dictionaries]\ndirectories = [{:?}]\nsystem_in_place = true\n",
+            system.display().to_string()
+        ))
+        .unwrap();
+        let provider = |lang: &str| LangProvider::in_dirs(lang, &config, dirs.clone());
+
+        let bundle = export_from(&[provider("fr"), provider("en_US")], Vec::new()).unwrap();
+        match export_from(&[provider("de_DE")], Vec::new()) {
+            Err(SpellError::DicNotFound(path)) => assert_eq!(path, src.join("de_DE.aff")),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert_eq!(
+            import_into(&dst, bundle.as_slice()).unwrap(),
+            vec!["en_US", "fr"]
+        );
+        for (name, dir) in &[("fr.aff", &src), ("fr.dic", &src), ("en_US.dic", &system)] {
+            assert_eq!(
+                fs::read(dst.join(name)).unwrap(),
+                fs::read(dir.join(name)).unwrap()
+            );
+        }
+        assert_eq!(
+            InstallInfo::read(&InstallInfo::path(&dst, "fr")),
+            Some(info)
+        );
+        assert!(!InstallInfo::path(&dst, "en_US").exists());
+
+        let mut builder = Builder::new(Vec::new());
+        for (path, content) in &[("nested/it.aff", "SET UTF-8\n"), ("notes.txt", "hello")] {
+            let mut header = Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        let bundle = builder.into_inner().unwrap();
+        assert!(import_into(&dst, bundle.as_slice()).unwrap().is_empty());
+        assert!(!dst.join("it.aff").exists() && !dst.join("notes.txt").exists());
+    }
+}
@@ -1,45 +1,393 @@
 use std::collections::HashMap;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::SpellError;
-use serde::Deserialize;
+use directories::BaseDirs;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+/// Where to download the dictionary of a language from.
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct SourceConfig {
+    /// URL of the `.aff` file, or its path in the archive.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub aff: String,
+    /// URL of the `.dic` file, or its path in the archive.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub dic: String,
+    /// Path or URL of a zip archive, e.g. a LibreOffice `.oxt` extension,
+    /// containing the dictionary. Without `aff` and `dic`, the dictionary is
+    /// found using the `dictionaries.xcu` file of the archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+    /// Expected SHA-256 digest of the `.aff` file or archive member, in
+    /// hexadecimal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aff_sha256: Option<String>,
+    /// Expected SHA-256 digest of the `.dic` file or archive member, in
+    /// hexadecimal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dic_sha256: Option<String>,
 }
 
-#[derive(Deserialize)]
+/// Where to find the dictionaries, the `[dictionaries]` table of a config
+/// file.
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct DictionariesConfig {
+    /// Directories of the system containing dictionaries.
+    #[serde(default)]
     pub directories: Vec<String>,
+    /// Directories containing dictionaries, relative to the home directory.
+    #[serde(default)]
+    pub user_directories: Vec<String>,
+    /// Whether UTF-8 dictionaries found in the directories are used in place
+    /// instead of being copied, the other ones are still converted. Unset
+    /// means no.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_in_place: Option<bool>,
+    /// Sources of the dictionaries to download, by language.
+    #[serde(default)]
     pub sources: HashMap<String, SourceConfig>,
 }
 
-#[derive(Deserialize)]
+impl DictionariesConfig {
+    /// Directories to search for dictionaries, in order of precedence.
+    ///
+    /// User directories are relative to the home directory and come first, so
+    /// that dictionaries installed by the user win over the system ones.
+    pub fn search_directories(&self) -> Vec<PathBuf> {
+        let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_owned());
+        let user = self.user_directories.iter().filter_map(|d| match &home {
+            Some(home) => Some(home.join(d)),
+            None => {
+                log::warn!("no home directory, ignoring user directory '{}'", d);
+                None
+            }
+        });
+        user.chain(self.directories.iter().map(PathBuf::from))
+            .collect()
+    }
+}
+
+/// Configuration of rspell.
+///
+/// A config file only holds the values it sets, it is merged over the
+/// defaults with [merge()](#method.merge), see
+/// [load_config()](fn.load_config.html).
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Config {
+    /// Language to use when none is given explicitly, several ones can be
+    /// separated by commas.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Patterns of words to accept, `*` matches any sequence of characters.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Extra words to accept.
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Where to find and download the dictionaries.
+    #[serde(default)]
     pub dictionaries: DictionariesConfig,
 }
 
+/// The built-in configuration.
+const DEFAULT_CONFIG: &str = include_str!("../files/config.toml");
+/// The config file created for the user, commented out, so that the
+/// built-in values keep following the updates of rspell.
+const USER_CONFIG: &str = include_str!("../files/user-config.toml");
+
+impl Default for Config {
+    /// The built-in configuration, with its dictionary directories and
+    /// sources.
+    fn default() -> Self {
+        toml::from_str(DEFAULT_CONFIG).expect("parse built-in config")
+    }
+}
+
+/// Name of the project configuration file.
+pub const PROJECT_CONFIG: &str = ".rspell.toml";
+/// Name of the project word list, one word per line.
+pub const PROJECT_WORDS: &str = ".rspell.dic";
+
+#[derive(Deserialize)]
+struct ProjectConfig {
+    lang: Option<String>,
+    #[serde(default)]
+    ignore: Vec<String>,
+    #[serde(default)]
+    words: Vec<String>,
+}
+
+impl Config {
+    /// Merges another configuration over this one.
+    ///
+    /// Its language and non-empty directory lists win, its sources replace the
+    /// ones of the same language, ignore patterns and words are added. Its
+    /// choice of using the system dictionaries in place wins when set.
+    pub fn merge(&mut self, other: Config) {
+        if other.lang.is_some() {
+            self.lang = other.lang;
+        }
+        self.ignore.extend(other.ignore);
+        self.words.extend(other.words);
+        let dicts = other.dictionaries;
+        if !dicts.directories.is_empty() {
+            self.dictionaries.directories = dicts.directories;
+        }
+        if !dicts.user_directories.is_empty() {
+            self.dictionaries.user_directories = dicts.user_directories;
+        }
+        self.dictionaries.sources.extend(dicts.sources);
+        if dicts.system_in_place.is_some() {
+            self.dictionaries.system_in_place = dicts.system_in_place;
+        }
+    }
+
+    /// Whether the word matches one of the ignore patterns.
+    pub fn ignores(&self, word: &str) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| wildcard_match(pattern, word))
+    }
+
+    /// Merges the project files found in the given directory over this
+    /// configuration.
+    ///
+    /// The project language wins, ignore patterns and words are added.
+    pub fn merge_project(&mut self, dir: &Path) -> Result<(), SpellError> {
+        let path = dir.join(PROJECT_CONFIG);
+        if path.exists() {
+            log::debug!("project config file: {}", path.display());
+            let raw = fs::read_to_string(&path).map_err(SpellError::ReadConfigError)?;
+            let project: ProjectConfig =
+                toml::from_str(&raw).map_err(SpellError::LoadConfigError)?;
+            // projects cannot change where dictionaries come from
+            self.merge(Config {
+                lang: project.lang,
+                ignore: project.ignore,
+                words: project.words,
+                dictionaries: DictionariesConfig::default(),
+            });
+        }
+        let path = dir.join(PROJECT_WORDS);
+        if path.exists() {
+            log::debug!("project word list: {}", path.display());
+            let raw = fs::read_to_string(&path).map_err(SpellError::ReadConfigError)?;
+            self.words.extend(parse_words(&raw));
+        }
+        Ok(())
+    }
+}
+
+/// Words of a word list, hunspell's `.dic` format is accepted too: the word
+/// count on the first line and affix flags are ignored.
+fn parse_words(raw: &str) -> impl Iterator<Item = String> + '_ {
+    raw.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(i, line)| {
+            let count = *i == 0 && line.chars().all(|c| c.is_ascii_digit());
+            !(line.is_empty() || line.starts_with('#') || count)
+        })
+        .map(|(_, line)| line.split('/').next().unwrap().to_string())
+}
+
+pub(crate) fn wildcard_match(pattern: &str, word: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == word,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            word.starts_with(prefix)
+                && (prefix.len()..=word.len())
+                    .filter(|&i| word.is_char_boundary(i))
+                    .any(|i| wildcard_match(rest, &word[i..]))
+        }
+    }
+}
+
+/// Finds the closest directory containing project files, looking from the
+/// given path up to the root.
+pub fn find_project<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    let path = path.as_ref();
+    let start = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    start
+        .ancestors()
+        .filter(|dir| dir.is_dir())
+        .find(|dir| dir.join(PROJECT_CONFIG).is_file() || dir.join(PROJECT_WORDS).is_file())
+        .map(Path::to_owned)
+}
+
 fn ensure_config_file(path: &PathBuf) -> io::Result<bool> {
     let create = !path.exists();
     if create {
         log::info!("no config file, creating one at '{}'", path.display());
-        fs::create_dir_all(&path.parent().unwrap())?;
-        fs::write(&path, include_str!("../files/config.toml"))?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, USER_CONFIG)?;
     }
     Ok(create)
 }
 
+/// Location of the config file read by [load_config()](fn.load_config.html).
+pub fn config_file() -> PathBuf {
+    crate::dirs::config_path().0
+}
+
+fn read_config(path: &Path) -> Result<Config, SpellError> {
+    log::debug!("config file: {}", path.display());
+    let raw = fs::read_to_string(path).map_err(SpellError::ReadConfigError)?;
+    let mut config = Config::default();
+    config.merge(toml::from_str(&raw).map_err(SpellError::LoadConfigError)?);
+    Ok(config)
+}
+
 /// Loads the configuration from the disk.
 ///
-/// If the config file is absent it will be created with default values.
+/// The config file is the one given by the `RSPELL_CONFIG` environment
+/// variable, if any. Otherwise, if the user config file is absent it will be
+/// created with commented out examples. It is merged over the built-in
+/// configuration, so that new built-in sources are available with an older
+/// file.
 pub fn load_config() -> Result<Config, SpellError> {
-    let path = crate::dirs().config_dir().join("config.toml");
-    log::debug!("config file: {}", path.display());
-    ensure_config_file(&path).map_err(SpellError::InitConfigError)?;
-    let raw = fs::read_to_string(&path).map_err(SpellError::ReadConfigError)?;
-    toml::from_str(&raw).map_err(SpellError::LoadConfigError)
+    let (path, from_env) = crate::dirs::config_path();
+    if !from_env {
+        ensure_config_file(&path).map_err(SpellError::InitConfigError)?;
+    }
+    read_config(&path)
+}
+
+/// Loads the configuration for the given file or directory.
+///
+/// The project files closest to it are merged over the user configuration.
+/// Unlike [load_config()](fn.load_config.html), the default values are used
+/// when the user config file is absent and nothing is written on the disk.
+pub fn load_project_config<P: AsRef<Path>>(path: P) -> Result<Config, SpellError> {
+    let (user, from_env) = crate::dirs::config_path();
+    let mut config = if from_env || user.exists() {
+        read_config(&user)?
+    } else {
+        Config::default()
+    };
+    if let Some(dir) = find_project(path) {
+        config.merge_project(&dir)?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn search_directories() {
+        let config: Config = toml::from_str(
+            r#"
+            [dictionaries]
+            directories = ["/usr/share/hunspell/"]
+            user_directories = [".enchant/"]
+            [dictionaries.sources]
+            "#,
+        )
+        .unwrap();
+        let home = BaseDirs::new().unwrap().home_dir().to_owned();
+        assert_eq!(
+            config.dictionaries.search_directories(),
+            vec![
+                home.join(".enchant/"),
+                PathBuf::from("/usr/share/hunspell/")
+            ]
+        );
+    }
+
+    #[test]
+    fn project() {
+        let tmp = TempDir::new("project");
+        let root = tmp.path().to_owned();
+        let nested = root.join("docs").join("guide");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            root.join(PROJECT_CONFIG),
+            "lang = \"en_GB\"\nignore = [\"0x*\"]\nwords = [\"rspell\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join(PROJECT_WORDS),
+            "2\nhunspell/S\n# comment\nmyspell\n",
+        )
+        .unwrap();
+        let found = find_project(nested.join("index.md")).unwrap();
+        assert_eq!(found, fs::canonicalize(&root).unwrap());
+
+        let mut config: Config =
+            toml::from_str("[dictionaries]\ndirectories = []\n[dictionaries.sources]\n").unwrap();
+        config.merge_project(&found).unwrap();
+        assert_eq!(config.lang.as_deref(), Some("en_GB"));
+        assert_eq!(config.words, vec!["rspell", "hunspell", "myspell"]);
+        assert!(config.ignores("0xdeadbeef"));
+        assert!(!config.ignores("deadbeef"));
+    }
+
+    #[test]
+    fn merge() {
+        let mut config = Config::default();
+        assert!(config.dictionaries.sources.contains_key("en_US"));
+        let user: Config = toml::from_str(
+            r#"
+            lang = "fr"
+            [dictionaries]
+            directories = ["/opt/dictionaries/"]
+            [dictionaries.sources.en_US]
+            archive = "https://example.org/en_US.oxt"
+            "#,
+        )
+        .unwrap();
+        let sources = config.dictionaries.sources.len();
+        config.merge(user);
+        assert_eq!(config.lang.as_deref(), Some("fr"));
+        assert_eq!(config.dictionaries.directories, vec!["/opt/dictionaries/"]);
+        assert!(!config.dictionaries.user_directories.is_empty());
+        assert_eq!(config.dictionaries.sources.len(), sources);
+        let source = &config.dictionaries.sources["en_US"];
+        assert_eq!(
+            source.archive.as_deref(),
+            Some("https://example.org/en_US.oxt")
+        );
+        assert!(source.aff.is_empty());
+        assert_eq!(config.dictionaries.system_in_place, Some(false));
+        for (raw, expected) in &[
+            ("[dictionaries]\nsystem_in_place = true\n", Some(true)),
+            ("lang = \"de\"\n", Some(true)),
+            ("[dictionaries]\nsystem_in_place = false\n", Some(false)),
+        ] {
+            config.merge(toml::from_str(raw).unwrap());
+            assert_eq!(config.dictionaries.system_in_place, *expected);
+        }
+
+        let raw = toml::to_string(&config).unwrap();
+        let parsed: Config = toml::from_str(&raw).unwrap();
+        assert_eq!(parsed.lang, config.lang);
+        assert_eq!(parsed.dictionaries.sources.len(), sources);
+    }
+
+    #[test]
+    fn user_template() {
+        let mut config = Config::default();
+        let sources = config.dictionaries.sources.len();
+        let template: Config = toml::from_str(USER_CONFIG).unwrap();
+        assert!(template.lang.is_none() && template.dictionaries.sources.is_empty());
+        assert!(template.dictionaries.directories.is_empty());
+        config.merge(template);
+        assert_eq!(config.dictionaries.sources.len(), sources);
+        assert_eq!(config.dictionaries.system_in_place, Some(false));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*_id", "user_id"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        assert!(!wildcard_match("a*b*c", "aXbY"));
+        assert!(wildcard_match("café*", "cafés"));
+    }
 }
@@ -1,51 +1,258 @@
-use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader, Read, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::config::Config;
+use crate::dirs::Dirs;
+use crate::download::{sanity_check, sha256_hex, Download, Validators};
+use crate::encoding::{convert, detect_encoding, encoding_for_label};
 use crate::hunspell::Hunspell;
+use crate::locale::Locale;
+use crate::provenance::{Location, Provenance};
 use crate::SpellError;
-use curl::easy::Easy;
 use encoding_rs::{Encoding, UTF_8};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
 
 // TODO log steps
 
+/// Downloads a file with the default settings unless it did not change since
+/// the download of `previous`, returns the origin of the new download.
+pub(crate) fn download_changed(
+    url: &str,
+    dst: &Path,
+    previous: Option<&SourceInfo>,
+) -> Result<Option<SourceInfo>, SpellError> {
+    let previous = previous.map(SourceInfo::validators);
+    let validators = Download::default().fetch(url, dst, previous.as_ref())?;
+    Ok(validators.map(|validators| SourceInfo {
+        source: url.to_string(),
+        etag: validators.etag,
+        last_modified: validators.last_modified,
+        ..SourceInfo::default()
+    }))
+}
+
+/// Origin of an installed dictionary file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceInfo {
+    /// URL, path or archive member (`archive#member`) of the source file.
+    pub source: String,
+    /// `ETag` of the downloaded file, if any.
+    pub etag: Option<String>,
+    /// `Last-Modified` date of the downloaded file, if any.
+    pub last_modified: Option<String>,
+    /// SHA-256 digest of the source file, before its conversion to UTF-8.
+    pub sha256: String,
+    /// Modification date of a source file of the system, in seconds since the
+    /// Unix epoch, to install it again when it changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+}
+
+impl SourceInfo {
+    fn validators(&self) -> Validators {
+        Validators {
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        }
+    }
+}
+
+/// Install metadata of a dictionary, stored next to its files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstallInfo {
+    /// Install date, in seconds since the Unix epoch.
+    pub installed: u64,
+    /// Encoding of the sources, converted to UTF-8.
+    #[serde(default)]
+    pub encoding: String,
+    /// Whether some characters of the sources were not valid in their
+    /// encoding and got replaced.
+    #[serde(default)]
+    pub lossy: bool,
+    /// Origin of the `.aff` file.
+    pub aff: SourceInfo,
+    /// Origin of the `.dic` file.
+    pub dic: SourceInfo,
+}
+
+impl InstallInfo {
+    /// Creates the metadata of a dictionary installed now.
+    pub(crate) fn new(
+        encoding: &'static Encoding,
+        lossy: bool,
+        aff: SourceInfo,
+        dic: SourceInfo,
+    ) -> InstallInfo {
+        InstallInfo {
+            installed: now(),
+            encoding: encoding.name().to_string(),
+            lossy,
+            aff,
+            dic,
+        }
+    }
+
+    /// Location of the metadata of the named dictionary in a directory.
+    pub(crate) fn path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{}.meta.toml", name))
+    }
+
+    /// Reads metadata, `None` when absent or unreadable.
+    pub(crate) fn read(path: &Path) -> Option<InstallInfo> {
+        let content = fs::read_to_string(path).ok()?;
+        toml::from_str(&content)
+            .map_err(|err| log::warn!("ignoring metadata {}: {}", path.display(), err))
+            .ok()
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> Result<(), SpellError> {
+        let content = toml::to_string(self).expect("serialize install metadata");
+        write_atomic(path, content.as_bytes()).map_err(SpellError::FileCachingError)
+    }
+}
+
+/// Modification date of a file, in seconds since the Unix epoch.
+pub(crate) fn modified(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A path next to the given one for a file being written, unique to the
+/// process and the call so that threads never write the same file.
+pub(crate) fn partial_path(path: &Path, ext: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(
+        ".{}.{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        ext
+    ));
+    PathBuf::from(tmp)
+}
+
+/// Writes a file next to its destination then renames it, so that readers
+/// never see a partially written file.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp = partial_path(path, "tmp");
+    let written = fs::write(&tmp, content)
+        .and_then(|_| File::open(&tmp)?.sync_all())
+        .and_then(|_| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Takes the advisory lock of the named dictionary in a directory, released
+/// when the returned file is dropped.
+///
+/// Installs, updates and removals of a dictionary hold it, so that several
+/// processes can install the same dictionary at once.
+pub(crate) fn lock_dictionary(dir: &Path, name: &str) -> Result<File, SpellError> {
+    fs::create_dir_all(dir).map_err(SpellError::FileCachingError)?;
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(format!("{}.lock", name)))
+        .map_err(SpellError::FileCachingError)?;
+    lock.lock_exclusive()
+        .map_err(SpellError::FileCachingError)?;
+    Ok(lock)
+}
+
+/// Installs a dictionary file from its raw content, converted to UTF-8 from
+/// the given encoding, returns whether the conversion was lossy.
+pub(crate) fn install_raw(
+    file_path: &Path,
+    raw: &[u8],
+    encoding: &'static Encoding,
+) -> Result<bool, SpellError> {
+    let is_aff = file_path.extension().is_some_and(|ext| ext == "aff");
+    let (content, lossy) = convert(raw, encoding, is_aff);
+    if lossy {
+        log::warn!(
+            "{}: invalid {} characters were replaced",
+            file_path.display(),
+            encoding.name()
+        );
+    }
+    fs::create_dir_all(file_path.parent().unwrap()).map_err(SpellError::FileCachingError)?;
+    write_atomic(file_path, content.as_bytes()).map_err(SpellError::ConversionError)?;
+    Ok(lossy)
+}
+
 struct FileProvider<'a> {
     file_path: &'a Path,
-    cache_path: &'a Path,
-    directories: &'a [String],
+    cache_path: PathBuf,
+    directories: &'a [PathBuf],
     url: Option<&'a str>,
+    sha256: Option<&'a str>,
 }
 
 impl<'a> FileProvider<'a> {
-    fn fetch(&self) -> Result<(), SpellError> {
+    fn fetch(&self, previous: Option<&Validators>) -> Result<Option<Validators>, SpellError> {
         match self.url {
             Some(url) => {
-                log::debug!("downloading file from {}", url);
-                // TODO error handling
-                fs::create_dir_all(&self.cache_path.parent().unwrap())
+                fs::create_dir_all(self.cache_path.parent().unwrap())
                     .map_err(SpellError::FileCachingError)?;
-                let mut dst =
-                    File::create(&self.cache_path).map_err(SpellError::FileCachingError)?;
-                let mut easy = Easy::new();
-                easy.url(url).unwrap();
-                let mut transfer = easy.transfer();
-                transfer
-                    .write_function(|data| {
-                        dst.write(data).unwrap();
-                        Ok(data.len())
-                    })
-                    .unwrap();
-                transfer.perform().unwrap();
-                Ok(())
+                let validators = Download::default().fetch(url, &self.cache_path, previous)?;
+                if validators.is_none() {
+                    return Ok(None);
+                }
+                let verified = self.verify(url);
+                if verified.is_err() {
+                    let _ = fs::remove_file(&self.cache_path);
+                }
+                verified.map(|_| validators)
             }
             None => Err(SpellError::NoDicSource),
         }
     }
 
+    /// Verifies the downloaded file against the expected checksum, if any, and
+    /// checks that it looks like a dictionary.
+    fn verify(&self, url: &str) -> Result<(), SpellError> {
+        let raw = fs::read(&self.cache_path).map_err(SpellError::FileCachingError)?;
+        if let Some(expected) = self.sha256 {
+            let actual = sha256_hex(&raw);
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                return Err(SpellError::ChecksumError {
+                    url: url.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+        let kind = self
+            .file_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        sanity_check(kind, &raw).map_err(|reason| SpellError::CorruptDicError {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+
     fn find(&self) -> Option<PathBuf> {
         for dir in self.directories {
-            let target = PathBuf::from(&dir).join(self.file_path.file_name().unwrap());
+            let target = dir.join(self.file_path.file_name().unwrap());
             if target.exists() {
                 return Some(target);
             }
@@ -53,111 +260,397 @@ impl<'a> FileProvider<'a> {
         None
     }
 
-    fn detect_encoding<P: AsRef<Path>>(path: P) -> io::Result<String> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-        let mut buf = Vec::new();
-        while reader.read_until(0x0A, &mut buf)? > 0 {
-            if buf.starts_with(b"SET ") {
-                let s = String::from_utf8(buf[4..].to_vec()).unwrap();
-                return Ok(String::from(s.trim_end()));
+    /// Puts the source file in the cache, from the disk or from internet.
+    ///
+    /// Returns `None` when the previous download is still up to date.
+    fn obtain(&self, previous: Option<&SourceInfo>) -> Result<Option<SourceInfo>, SpellError> {
+        let (source, validators, modified) = match self.find() {
+            Some(src) => {
+                log::debug!("found source file: {}", src.display());
+                fs::create_dir_all(self.cache_path.parent().unwrap())
+                    .map_err(SpellError::FileCachingError)?;
+                fs::copy(&src, &self.cache_path).map_err(SpellError::FileCachingError)?;
+                let modified = modified(&src);
+                (src.display().to_string(), Validators::default(), modified)
             }
-            buf.clear();
-        }
-        Ok(String::from("utf-8"))
+            None => {
+                // validators only apply to the same URL
+                let previous = previous
+                    .filter(|p| Some(p.source.as_str()) == self.url)
+                    .map(SourceInfo::validators);
+                match self.fetch(previous.as_ref())? {
+                    Some(validators) => (self.url.unwrap().to_string(), validators, None),
+                    None => return Ok(None),
+                }
+            }
+        };
+        let raw = fs::read(&self.cache_path).map_err(SpellError::FileCachingError)?;
+        Ok(Some(SourceInfo {
+            source,
+            etag: validators.etag,
+            last_modified: validators.last_modified,
+            sha256: sha256_hex(&raw),
+            modified,
+        }))
     }
 
-    fn read_raw_source(&self) -> io::Result<Vec<u8>> {
-        let mut raw = Vec::new();
-        File::open(&self.cache_path)?.read_to_end(&mut raw)?;
-        Ok(raw)
+    /// Installs the source file in the cache, returns whether the conversion
+    /// was lossy.
+    fn install(&self, encoding: &'static Encoding) -> Result<bool, SpellError> {
+        let raw = fs::read(&self.cache_path).map_err(SpellError::ConversionError)?;
+        log::info!("installing {}", self.file_path.display());
+        install_raw(self.file_path, &raw, encoding)
     }
+}
 
-    fn convert(&self) -> Result<(), SpellError> {
-        if !self.cache_path.exists() {
-            if let Some(src) = self.find() {
-                log::debug!("found source file: {}", src.display());
-                fs::create_dir_all(&self.cache_path.parent().unwrap())
-                    .map_err(SpellError::FileCachingError)?;
-                fs::copy(src, self.cache_path).map_err(SpellError::FileCachingError)?;
-            }
-        }
-        if !self.cache_path.exists() {
-            self.fetch()?;
-        }
-        let encoding =
-            Self::detect_encoding(&self.cache_path).map_err(SpellError::ConversionError)?;
-        log::debug!("detected encoding: {}", encoding);
-        let decoder = Encoding::for_label(encoding.as_bytes()).unwrap();
-        log::debug!("using decoder: {}", decoder.name());
-        let raw = self
-            .read_raw_source()
-            .map_err(SpellError::ConversionError)?;
-        fs::create_dir_all(&self.file_path.parent().unwrap())
-            .map_err(SpellError::FileCachingError)?;
-        if decoder == UTF_8 {
-            fs::write(&self.file_path, raw).map_err(SpellError::ConversionError)?;
-        } else {
-            let (content, _, _) = decoder.decode(&raw);
-            let content_tweaked = content.replace(&format!("SET {}", encoding), "SET UTF-8");
-            fs::write(&self.file_path, content_tweaked.as_bytes())
-                .map_err(SpellError::ConversionError)?;
+/// Installs the files of a dictionary from their sources, unless they did not
+/// change since the previous install.
+///
+/// Both files are converted from the encoding of the `.aff` source. Returns
+/// the metadata of the install and whether the files changed.
+fn install_files(
+    aff: &FileProvider,
+    dic: &FileProvider,
+    previous: Option<&InstallInfo>,
+) -> Result<(InstallInfo, bool), SpellError> {
+    // missing files are installed again from scratch, as are the ones of
+    // unknown encoding
+    let previous = previous
+        .filter(|p| !p.encoding.is_empty() && aff.file_path.exists() && dic.file_path.exists());
+    let installed = install_sources(aff, dic, previous);
+    for provider in &[aff, dic] {
+        let _ = fs::remove_file(&provider.cache_path);
+    }
+    installed
+}
+
+fn install_sources(
+    aff: &FileProvider,
+    dic: &FileProvider,
+    previous: Option<&InstallInfo>,
+) -> Result<(InstallInfo, bool), SpellError> {
+    let aff_source = aff.obtain(previous.map(|p| &p.aff))?;
+    let dic_source = dic.obtain(previous.map(|p| &p.dic))?;
+    let changed = |source: &Option<SourceInfo>, previous: Option<&SourceInfo>| match source {
+        Some(source) => previous.is_none_or(|p| p.sha256 != source.sha256),
+        None => false,
+    };
+    let aff_changed = changed(&aff_source, previous.map(|p| &p.aff));
+    let dic_changed = changed(&dic_source, previous.map(|p| &p.dic));
+    let encoding = match (&aff_source, previous) {
+        (Some(_), _) => {
+            detect_encoding(&fs::read(&aff.cache_path).map_err(SpellError::ConversionError)?)?
         }
-        Ok(())
+        (None, Some(previous)) => encoding_for_label(&previous.encoding)
+            .ok_or_else(|| SpellError::UnknownEncodingError(previous.encoding.clone()))?,
+        (None, None) => unreachable!("conditional download without validators"),
+    };
+    // a file kept as is keeps its lossiness
+    let mut lossy = previous.is_some_and(|p| p.lossy) && !(aff_changed && dic_changed);
+    if aff_changed {
+        lossy |= aff.install(encoding)?;
     }
+    if dic_changed {
+        lossy |= dic.install(encoding)?;
+    }
+    let changed = aff_changed || dic_changed;
+    let info = InstallInfo {
+        installed: match previous {
+            Some(previous) if !changed => previous.installed,
+            _ => now(),
+        },
+        encoding: encoding.name().to_string(),
+        lossy,
+        aff: aff_source
+            .or_else(|| previous.map(|p| p.aff.clone()))
+            .unwrap_or_default(),
+        dic: dic_source
+            .or_else(|| previous.map(|p| p.dic.clone()))
+            .unwrap_or_default(),
+    };
+    Ok((info, changed))
+}
+
+/// Names of the `.aff` dictionaries in the given directory.
+pub(crate) fn aff_names(dir: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "aff" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(String::from)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Finds the files of a dictionary in the given directories, when its `.aff`
+/// declares UTF-8 and they can be used in place.
+fn system_utf8(directories: &[PathBuf], name: &str) -> Option<(PathBuf, PathBuf)> {
+    directories
+        .iter()
+        .map(|dir| {
+            (
+                dir.join(format!("{}.aff", name)),
+                dir.join(format!("{}.dic", name)),
+            )
+        })
+        .find(|(aff, dic)| aff.exists() && dic.exists())
+        .filter(|(aff, _)| {
+            fs::read(aff)
+                .ok()
+                .and_then(|raw| detect_encoding(&raw).ok())
+                .is_some_and(|encoding| encoding == UTF_8)
+        })
+}
 
-    fn ensure(&self) -> Result<(), SpellError> {
-        if !self.file_path.exists() {
-            self.convert()?;
-            fs::remove_file(&self.cache_path).map_err(SpellError::FileCachingError)?;
+/// Chooses the dictionary to use for a language among the ones in the given
+/// directories and the other available names.
+///
+/// An exact match wins, then the language tag is normalised and its
+/// [fallbacks](struct.Locale.html#method.fallbacks) are tried.
+pub(crate) fn choose_dictionary<'a, I>(lang: &str, directories: I, others: &[String]) -> String
+where
+    I: IntoIterator<Item = &'a Path>,
+{
+    let mut available: Vec<String> = directories.into_iter().flat_map(aff_names).collect();
+    available.extend(others.iter().cloned());
+    if available.iter().any(|name| name == lang) {
+        return lang.to_string();
+    }
+    let chosen = match Locale::parse(lang) {
+        Some(locale) => locale
+            .choose(&available)
+            .map_or_else(|| locale.to_string(), String::from),
+        None => lang.to_string(),
+    };
+    if chosen != lang {
+        log::info!("using dictionary {} for {}", chosen, lang);
+    }
+    chosen
+}
+
+pub(crate) fn dictionaries_dir() -> PathBuf {
+    Dirs::from_env().dictionaries()
+}
+
+/// Metadata of a dictionary found on the disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DictionaryInfo {
+    /// Name of the dictionary, the file name without extension.
+    pub name: String,
+    /// Location of the `.aff` file.
+    pub aff: PathBuf,
+    /// Location of the `.dic` file.
+    pub dic: PathBuf,
+    /// Size of both files, in bytes.
+    pub size: u64,
+    /// Encoding declared by `SET` in the `.aff` file, detected from its
+    /// content otherwise.
+    pub encoding: String,
+    /// Language declared by `LANG` in the `.aff` file, if any.
+    pub lang: Option<String>,
+}
+
+impl DictionaryInfo {
+    /// Reads the metadata of the dictionary made of the given files.
+    pub fn read<P: Into<PathBuf>>(aff: P, dic: P) -> io::Result<DictionaryInfo> {
+        let (aff, dic) = (aff.into(), dic.into());
+        let raw = fs::read(&aff)?;
+        let size = raw.len() as u64 + fs::metadata(&dic)?.len();
+        let mut encoding = None;
+        let mut lang = None;
+        for line in raw.split(|byte| *byte == b'\n') {
+            if encoding.is_some() && lang.is_some() {
+                break;
+            }
+            let line = String::from_utf8_lossy(line);
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some("SET"), Some(value)) => encoding = Some(value.to_string()),
+                (Some("LANG"), Some(value)) => lang = Some(value.to_string()),
+                _ => {}
+            }
         }
-        log::debug!("file available at {}", self.file_path.display());
-        Ok(())
+        // detected the same way as when the dictionary is installed
+        let encoding = encoding.unwrap_or_else(|| {
+            detect_encoding(&raw).map_or_else(|_| String::new(), |e| e.name().to_string())
+        });
+        Ok(DictionaryInfo {
+            name: aff
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            aff,
+            dic,
+            size,
+            encoding,
+            lang,
+        })
     }
 }
 
+/// A dictionary available from the sources of the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteDictionary {
+    /// Name of the dictionary.
+    pub name: String,
+    /// URL of the `.aff` file, or its path in the archive.
+    pub aff_url: String,
+    /// URL of the `.dic` file, or its path in the archive.
+    pub dic_url: String,
+    /// Path or URL of the archive containing the dictionary, its member paths
+    /// are empty when it is found using its `dictionaries.xcu` file.
+    pub archive: Option<String>,
+}
+
+fn dictionaries_in(dir: &Path) -> Vec<DictionaryInfo> {
+    aff_names(dir)
+        .into_iter()
+        .filter_map(|name| {
+            let aff = dir.join(format!("{}.aff", name));
+            let dic = dir.join(format!("{}.dic", name));
+            if !dic.exists() {
+                return None;
+            }
+            DictionaryInfo::read(aff, dic)
+                .map_err(|err| log::warn!("skipping dictionary {}: {}", name, err))
+                .ok()
+        })
+        .collect()
+}
+
+/// Lists the dictionaries installed in the rspell data directory.
+pub fn installed_dictionaries() -> Vec<DictionaryInfo> {
+    dictionaries_in(&dictionaries_dir())
+}
+
+/// Lists the dictionaries found in the directories of the configuration,
+/// ordered by precedence.
+pub fn system_dictionaries(config: &Config) -> Vec<DictionaryInfo> {
+    config
+        .dictionaries
+        .search_directories()
+        .iter()
+        .flat_map(|dir| dictionaries_in(dir))
+        .collect()
+}
+
+/// Lists the dictionaries available from the sources of the configuration.
+pub fn remote_dictionaries(config: &Config) -> Vec<RemoteDictionary> {
+    let mut remote: Vec<RemoteDictionary> = config
+        .dictionaries
+        .sources
+        .iter()
+        .map(|(name, source)| RemoteDictionary {
+            name: name.clone(),
+            aff_url: source.aff.clone(),
+            dic_url: source.dic.clone(),
+            archive: source.archive.clone(),
+        })
+        .collect();
+    remote.sort_by(|a, b| a.name.cmp(&b.name));
+    remote
+}
+
 /// A structure to ensure that the data files for the given language are available.
 ///
 /// It can look for files on the disk or download directly from internet when possible.
 pub struct LangProvider<'a> {
-    lang: &'a str,
+    lang: String,
     config: &'a Config,
+    dirs: Dirs,
+    directories: Vec<PathBuf>,
     aff_path: PathBuf,
     dic_path: PathBuf,
+    in_place: bool,
 }
 
 impl LangProvider<'_> {
     /// Creates a new `LanguageProvider` for the given language and configuration.
     ///
     /// The configuration can be loaded using [load_config()](fn.load_config.html).
-    pub fn new<'a>(lang: &'a str, config: &'a Config) -> LangProvider<'a> {
-        let dics_dir = crate::dirs().data_local_dir().join("dictionaries");
+    ///
+    /// The language code is normalised and falls back to a less specific or
+    /// sibling dictionary when needed, e.g. `fr-ca` may use `fr` or `fr_FR`.
+    /// The installed dictionaries, the ones on the disk and the sources of
+    /// the configuration are considered.
+    ///
+    /// The data and cache directories can be set with the `RSPELL_DATA_DIR`
+    /// and `RSPELL_CACHE_DIR` environment variables.
+    ///
+    /// With the `system_in_place` option, a UTF-8 dictionary found on the disk
+    /// is used where it is instead of being installed.
+    pub fn new<'a>(lang: &str, config: &'a Config) -> LangProvider<'a> {
+        Self::in_dirs(lang, config, Dirs::from_env())
+    }
+
+    pub(crate) fn in_dirs<'a>(lang: &str, config: &'a Config, dirs: Dirs) -> LangProvider<'a> {
+        let dics_dir = dirs.dictionaries();
+        let directories = config.dictionaries.search_directories();
+        let sources: Vec<String> = config.dictionaries.sources.keys().cloned().collect();
+        let lang = choose_dictionary(
+            lang,
+            std::iter::once(dics_dir.as_path()).chain(directories.iter().map(PathBuf::as_path)),
+            &sources,
+        );
+        let system = if config.dictionaries.system_in_place == Some(true) {
+            system_utf8(&directories, &lang)
+        } else {
+            None
+        };
+        let in_place = system.is_some();
+        let (aff_path, dic_path) = system.unwrap_or_else(|| {
+            (
+                dics_dir.join(format!("{}.aff", lang)),
+                dics_dir.join(format!("{}.dic", lang)),
+            )
+        });
         LangProvider {
+            aff_path,
+            dic_path,
+            in_place,
             lang,
             config,
-            aff_path: dics_dir.join(&format!("{}.aff", lang)),
-            dic_path: dics_dir.join(&format!("{}.dic", lang)),
+            dirs,
+            directories,
         }
     }
 
+    /// Whether the dictionaries are the ones of the system, used in place.
+    pub fn in_place(&self) -> bool {
+        self.in_place
+    }
+
+    /// Name of the dictionary actually chosen for the language.
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
     /// Location of the `.aff` dictionary.
     pub fn aff(&self) -> &Path {
         &self.aff_path
     }
 
     fn on_disk(&self, filename: &std::ffi::OsStr) -> Vec<PathBuf> {
-        self.config
-            .dictionaries
-            .directories
+        self.directories
             .iter()
-            .map(|d| PathBuf::from(d).join(filename))
+            .map(|d| d.join(filename))
             .filter(|p| p.exists())
             .collect()
     }
 
     /// Search for corresponding `.aff` dictionaries on the disk.
+    ///
+    /// Results are ordered by precedence, user directories first.
     pub fn aff_on_disk(&self) -> Vec<PathBuf> {
-        self.on_disk(&self.aff_path.file_name().unwrap())
+        self.on_disk(self.aff_path.file_name().unwrap())
     }
 
     /// Location of the `.dic` dictionary.
@@ -167,46 +660,437 @@ impl LangProvider<'_> {
 
     /// Search for corresponding `.dic` dictionaries on the disk.
     pub fn dic_on_disk(&self) -> Vec<PathBuf> {
-        self.on_disk(&self.dic_path.file_name().unwrap())
+        self.on_disk(self.dic_path.file_name().unwrap())
+    }
+
+    fn providers(&self) -> (FileProvider<'_>, FileProvider<'_>) {
+        let sources = self.config.dictionaries.sources.get(&self.lang);
+        let cache_dir = &self.dirs.cache;
+        let aff = FileProvider {
+            file_path: &self.aff_path,
+            cache_path: cache_dir.join(self.aff_path.file_name().unwrap()),
+            directories: &self.directories,
+            url: sources.map(|s| s.aff.as_str()),
+            sha256: sources.and_then(|s| s.aff_sha256.as_deref()),
+        };
+        let dic = FileProvider {
+            file_path: &self.dic_path,
+            cache_path: cache_dir.join(self.dic_path.file_name().unwrap()),
+            directories: &self.directories,
+            url: sources.map(|s| s.dic.as_str()),
+            sha256: sources.and_then(|s| s.dic_sha256.as_deref()),
+        };
+        (aff, dic)
+    }
+
+    /// Location of the install metadata, in the dictionaries directory.
+    pub(crate) fn info_path(&self) -> PathBuf {
+        InstallInfo::path(&self.dirs.dictionaries(), &self.lang)
+    }
+
+    fn lock(&self) -> Result<File, SpellError> {
+        lock_dictionary(&self.dirs.dictionaries(), &self.lang)
+    }
+
+    /// Whether a file of the system the dictionaries were installed from
+    /// changed since, e.g. with an update of its package.
+    fn system_source_changed(&self) -> bool {
+        self.install_info().is_some_and(|info| {
+            [&info.aff, &info.dic].iter().any(|source| {
+                source.modified.is_some_and(|installed| {
+                    let current = modified(Path::new(&source.source));
+                    current.is_some_and(|current| current != installed)
+                })
+            })
+        })
+    }
+
+    /// Install metadata of the dictionaries, if they were installed with it.
+    pub fn install_info(&self) -> Option<InstallInfo> {
+        InstallInfo::read(&self.info_path())
+    }
+
+    /// Origin and checksums of the installed files, see
+    /// [Spell::provenance()](struct.Spell.html#method.provenance).
+    pub fn provenance(&self) -> Result<Provenance, SpellError> {
+        let location = if self.in_place {
+            Location::System
+        } else {
+            Location::Installed
+        };
+        Provenance::of(&self.lang, &self.aff_path, &self.dic_path, location)
     }
 
     /// Ensures that the dictionaries are present in the dictionaries directory.
     ///
     /// If one of the dictionaries is absent it will be fetched from the disk or
-    /// directly from internet when possible.
+    /// directly from internet when possible. Sources with an archive install
+    /// it from the archive, see [install_archive()](fn.install_archive.html).
+    ///
+    /// The origin of the installed files is recorded, see
+    /// [install_info()](#method.install_info).
+    ///
+    /// Installs hold a lock per language and files are renamed once complete,
+    /// so several processes can ensure the same language at once.
     pub fn ensure_data(&self) -> Result<(), SpellError> {
-        let sources = self.config.dictionaries.sources.get(self.lang);
-        let cache_dir = crate::dirs().cache_dir().to_owned();
-        FileProvider {
-            file_path: &self.aff_path,
-            cache_path: &cache_dir.join(self.aff_path.file_name().unwrap()),
-            directories: &self.config.dictionaries.directories,
-            url: sources.map(|s| s.aff.as_str()),
+        self.ensure().map(|_| ())
+    }
+
+    /// Same as [ensure_data()](#method.ensure_data), returns whether the
+    /// dictionaries were installed by this call.
+    pub(crate) fn ensure(&self) -> Result<bool, SpellError> {
+        // complete files are renamed in place, no need to wait for the lock
+        if self.aff_path.exists() && self.dic_path.exists() {
+            if !self.in_place && self.system_source_changed() {
+                log::info!(
+                    "system files of {} changed, installing them again",
+                    self.lang
+                );
+                return self.update();
+            }
+            return Ok(false);
         }
-        .ensure()?;
-        FileProvider {
-            file_path: &self.dic_path,
-            cache_path: &cache_dir.join(self.dic_path.file_name().unwrap()),
-            directories: &self.config.dictionaries.directories,
-            url: sources.map(|s| s.dic.as_str()),
+        let sources = self.config.dictionaries.sources.get(&self.lang);
+        if let Some(archive) = sources.and_then(|s| s.archive.as_deref()) {
+            return crate::archive::install_source(
+                &self.lang,
+                archive,
+                sources.unwrap(),
+                &self.dirs,
+            );
+        }
+        let _lock = self.lock()?;
+        // installed by another process in the meantime
+        if self.aff_path.exists() && self.dic_path.exists() {
+            return Ok(false);
         }
-        .ensure()
+        let (aff, dic) = self.providers();
+        let (info, _) = install_files(&aff, &dic, None)?;
+        info.write(&self.info_path())?;
+        Ok(true)
     }
 
-    /// Removes the dictionaries from the dictionaries directory.
-    pub fn remove_data(&self) -> Result<(), SpellError> {
-        if self.aff_path.exists() {
-            fs::remove_file(&self.aff_path).map_err(SpellError::RemoveDicError)?;
+    /// Updates the dictionaries if their sources changed since they were
+    /// installed, installing them if needed.
+    ///
+    /// Downloads are conditional on the recorded `ETag` and `Last-Modified`
+    /// headers and the files are converted again only when their checksum
+    /// changed. Returns whether the dictionaries changed.
+    pub fn update(&self) -> Result<bool, SpellError> {
+        if self.in_place {
+            return Ok(false);
         }
-        if self.dic_path.exists() {
-            fs::remove_file(&self.dic_path).map_err(SpellError::RemoveDicError)?;
+        let sources = self.config.dictionaries.sources.get(&self.lang);
+        if let Some(archive) = sources.and_then(|s| s.archive.as_deref()) {
+            return crate::archive::update_source(
+                &self.lang,
+                archive,
+                sources.unwrap(),
+                &self.dirs,
+                self.install_info().as_ref(),
+            );
+        }
+        let _lock = self.lock()?;
+        let (aff, dic) = self.providers();
+        let (info, changed) = install_files(&aff, &dic, self.install_info().as_ref())?;
+        info.write(&self.info_path())?;
+        Ok(changed)
+    }
+
+    /// Removes the dictionaries from the dictionaries directory, the ones of
+    /// the system used in place are left alone.
+    pub fn remove_data(&self) -> Result<(), SpellError> {
+        let _lock = self.lock()?;
+        let dir = self.dirs.dictionaries();
+        for ext in &["aff", "dic", "meta.toml", "used"] {
+            let path = dir.join(format!("{}.{}", self.lang, ext));
+            if path.exists() {
+                fs::remove_file(path).map_err(SpellError::RemoveDicError)?;
+            }
         }
         Ok(())
     }
 }
 
-impl Into<Hunspell> for LangProvider<'_> {
-    fn into(self) -> Hunspell {
-        Hunspell::new(self.aff_path, self.dic_path)
+impl From<LangProvider<'_>> for Hunspell {
+    fn from(val: LangProvider<'_>) -> Self {
+        Hunspell::new(val.aff_path, val.dic_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{serve, serve_headers, TempDir};
+    use std::thread;
+    use std::time::Duration;
+
+    fn provider_fetch(
+        dir: &TempDir,
+        url: &str,
+        name: &str,
+        sha256: Option<&str>,
+    ) -> Result<(), SpellError> {
+        let cache_path = dir.join(name);
+        let result = FileProvider {
+            file_path: Path::new(name),
+            cache_path: cache_path.clone(),
+            directories: &[],
+            url: Some(url),
+            sha256,
+        }
+        .fetch(None)
+        .map(|_| ());
+        assert_eq!(result.is_ok(), cache_path.exists());
+        result
+    }
+
+    #[test]
+    fn verify_downloads() {
+        let aff = "# affixes\nSET UTF-8\nSFX S Y 1\n";
+        let (url, _) = serve(vec![(200, aff), (200, aff), (200, "<!DOCTYPE html>")]);
+        let digest = sha256_hex(aff.as_bytes());
+        let dir = TempDir::new("verify-downloads");
+        provider_fetch(
+            &dir,
+            &format!("{}/a.aff", url),
+            "ok.aff",
+            Some(&digest.to_uppercase()),
+        )
+        .unwrap();
+        match provider_fetch(&dir, &format!("{}/a.aff", url), "sum.aff", Some("00")) {
+            Err(SpellError::ChecksumError { actual, .. }) => assert_eq!(actual, digest),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match provider_fetch(&dir, &format!("{}/a.dic", url), "html.dic", None) {
+            Err(SpellError::CorruptDicError { reason, .. }) => assert_eq!(reason, "HTML content"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    fn providers<'a>(
+        dir: &'a Path,
+        name: &str,
+        directories: &'a [PathBuf],
+        urls: Option<(&'a str, &'a str)>,
+    ) -> (
+        PathBuf,
+        PathBuf,
+        impl Fn(&'a Path, bool) -> FileProvider<'a>,
+    ) {
+        let cache = dir.join(format!("{}.src", name));
+        let provider = move |file_path: &'a Path, is_aff: bool| FileProvider {
+            file_path,
+            cache_path: cache.with_extension(if is_aff { "aff.src" } else { "dic.src" }),
+            directories,
+            url: urls.map(|(aff, dic)| if is_aff { aff } else { dic }),
+            sha256: None,
+        };
+        (
+            dir.join(format!("{}.aff", name)),
+            dir.join(format!("{}.dic", name)),
+            provider,
+        )
+    }
+
+    #[test]
+    fn update_files() {
+        let modified = "Last-Modified: Wed, 21 Oct 2026 07:28:00 GMT\r\n";
+        let aff_body: &[u8] = b"SET ISO8859-1\nTRY e\n";
+        let dic_body: &[u8] = b"1\ncaf\xe9\n";
+        let (url, requests) = serve_headers(vec![
+            (200, modified, aff_body),
+            (200, "ETag: \"v1\"\r\n", dic_body),
+            (304, "", b""),
+            (304, "", b""),
+            (304, "", b""),
+            (200, "ETag: \"v2\"\r\n", dic_body),
+            (304, "", b""),
+            (200, "ETag: \"v3\"\r\n", b"2\ncaf\xe9\nd\xe9j\xe0\n"),
+        ]);
+        let (aff_url, dic_url) = (format!("{}/up.aff", url), format!("{}/up.dic", url));
+        let dir = TempDir::new("update-files");
+        let (aff_path, dic_path, provider) =
+            providers(dir.path(), "up", &[], Some((&aff_url, &dic_url)));
+        let (aff, dic) = (provider(&aff_path, true), provider(&dic_path, false));
+
+        let (installed, changed) = install_files(&aff, &dic, None).unwrap();
+        assert!(changed);
+        assert_eq!(installed.encoding, "windows-1252");
+        assert_eq!(installed.dic.source, dic_url);
+        assert_eq!(installed.dic.sha256, sha256_hex(dic_body));
+        assert_eq!(fs::read_to_string(&aff_path).unwrap(), "SET UTF-8\nTRY e\n");
+        assert_eq!(fs::read_to_string(&dic_path).unwrap(), "1\ncafé\n");
+
+        let (info, changed) = install_files(&aff, &dic, Some(&installed)).unwrap();
+        assert!(!changed);
+        assert_eq!(info, installed);
+        assert!(requests.lock().unwrap()[2].contains("If-Modified-Since: Wed, 21 Oct"));
+        assert!(requests.lock().unwrap()[3].contains("If-None-Match: \"v1\""));
+
+        let (info, changed) = install_files(&aff, &dic, Some(&installed)).unwrap();
+        assert!(!changed);
+        assert_eq!(info.installed, installed.installed);
+        assert_eq!(info.dic.etag.as_deref(), Some("\"v2\""));
+
+        let (info, changed) = install_files(&aff, &dic, Some(&info)).unwrap();
+        assert!(changed);
+        assert_eq!(info.dic.etag.as_deref(), Some("\"v3\""));
+        assert_eq!(fs::read_to_string(&dic_path).unwrap(), "2\ncafé\ndéjà\n");
+        assert_eq!(requests.lock().unwrap().len(), 8);
+        assert!(!aff.cache_path.exists() && !dic.cache_path.exists());
+    }
+
+    #[test]
+    fn concurrent_install() {
+        let base = TempDir::new("concurrent-install");
+        let config: Config = toml::from_str("[dictionaries]\ndirectories = [\"files\"]\n").unwrap();
+        let dirs = Dirs::in_data_dir(base.join("data"));
+        let installed = thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        LangProvider::in_dirs("fr", &config, dirs.clone())
+                            .ensure()
+                            .unwrap()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .filter(|installed| *installed)
+                .count()
+        });
+        assert_eq!(installed, 1);
+        let dir = dirs.dictionaries();
+        assert_eq!(
+            fs::read(dir.join("fr.dic")).unwrap(),
+            fs::read("files/fr.dic").unwrap()
+        );
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["fr.aff", "fr.dic", "fr.lock", "fr.meta.toml"]);
+    }
+
+    #[test]
+    fn install_info() {
+        let source = |name: &str| SourceInfo {
+            source: format!("https://example.org/{}", name),
+            etag: Some(String::from("\"abc\"")),
+            last_modified: None,
+            sha256: sha256_hex(name.as_bytes()),
+            modified: None,
+        };
+        let info = InstallInfo::new(UTF_8, false, source("en_US.aff"), source("en_US.dic"));
+        let dir = TempDir::new("install-info");
+        let path = InstallInfo::path(dir.path(), "en_US");
+        assert_eq!(path, dir.join("en_US.meta.toml"));
+        assert_eq!(InstallInfo::read(&path), None);
+        info.write(&path).unwrap();
+        assert_eq!(InstallInfo::read(&path), Some(info));
+        fs::write(&path, "installed = \"yesterday\"").unwrap();
+        assert_eq!(InstallInfo::read(&path), None);
+    }
+
+    #[test]
+    fn remote() {
+        let config: Config = toml::from_str(
+            r#"
+            [dictionaries.sources.fr]
+            archive = "https://example.org/dict-fr.oxt"
+            [dictionaries.sources.de]
+            aff = "https://example.org/de.aff"
+            dic = "https://example.org/de.dic"
+            "#,
+        )
+        .unwrap();
+        let remote = remote_dictionaries(&config);
+        assert_eq!(remote[0].dic_url, "https://example.org/de.dic");
+        assert_eq!(remote[0].archive, None);
+        assert_eq!(remote[1].name, "fr");
+        assert_eq!(
+            remote[1].archive.as_deref(),
+            Some("https://example.org/dict-fr.oxt")
+        );
+    }
+
+    #[test]
+    fn dictionary_info() {
+        let dicts = dictionaries_in(Path::new("files"));
+        let names: Vec<&str> = dicts.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["en_US", "fr"]);
+        assert_eq!(dicts[0].encoding, "UTF-8");
+        assert_eq!(dicts[1].dic, Path::new("files/fr.dic"));
+        assert!(dicts[1].size > fs::metadata("files/fr.dic").unwrap().len());
+    }
+
+    #[test]
+    fn system_in_place() {
+        let base = TempDir::new("in-place");
+        let system = base.join("system");
+        fs::create_dir_all(&system).unwrap();
+        fs::copy("files/en_US.aff", system.join("en_US.aff")).unwrap();
+        fs::copy("files/en_US.dic", system.join("en_US.dic")).unwrap();
+        fs::write(system.join("la.aff"), b"SET ISO8859-1\nTRY aeiou\n").unwrap();
+        fs::write(system.join("la.dic"), b"1\ncaf\xe9\n").unwrap();
+        // pretend the system files are old, to see them change
+        for name in &["la.aff", "la.dic"] {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(system.join(name))
+                .unwrap();
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000))
+                .unwrap();
+        }
+        let config: Config = toml::from_str(&format!(
+            "[dictionaries]\ndirectories = [{:?}]\nsystem_in_place = true\n",
+            system.display().to_string()
+        ))
+        .unwrap();
+        let dirs = Dirs::in_data_dir(base.join("data"));
+
+        let en = LangProvider::in_dirs("en_US", &config, dirs.clone());
+        assert!(en.in_place());
+        assert_eq!(en.aff(), system.join("en_US.aff"));
+        en.ensure_data().unwrap();
+        assert!(!en.update().unwrap());
+        assert_eq!(
+            en.provenance().unwrap().dic.origin,
+            crate::Origin::System(system.join("en_US.dic"))
+        );
+        en.remove_data().unwrap();
+        assert!(system.join("en_US.aff").exists() && system.join("en_US.dic").exists());
+        assert!(!dirs.dictionaries().join("en_US.aff").exists());
+
+        let la = LangProvider::in_dirs("la", &config, dirs.clone());
+        assert!(!la.in_place());
+        la.ensure_data().unwrap();
+        assert_eq!(fs::read_to_string(la.dic()).unwrap(), "1\ncafé\n");
+        let info = la.install_info().unwrap();
+        assert_eq!(info.dic.modified, Some(1_000_000));
+        fs::write(system.join("la.dic"), b"2\ncaf\xe9\nna\xefve\n").unwrap();
+        la.ensure_data().unwrap();
+        assert_eq!(fs::read_to_string(la.dic()).unwrap(), "2\ncafé\nnaïve\n");
+        assert_ne!(la.install_info().unwrap().dic.modified, Some(1_000_000));
+
+        // without SET, the encoding is detected from the content
+        fs::write(system.join("eo.aff"), b"TRY aeiou\n").unwrap();
+        fs::write(system.join("eo.dic"), "1\nĉevalo\n").unwrap();
+        fs::write(system.join("ga.aff"), b"TRY a\xe1e\n").unwrap();
+        fs::write(system.join("ga.dic"), b"1\nc\xe1\n").unwrap();
+        let info = |name: &str| {
+            let path = |ext: &str| system.join(format!("{}.{}", name, ext));
+            DictionaryInfo::read(path("aff"), path("dic"))
+                .unwrap()
+                .encoding
+        };
+        assert_eq!(info("eo"), "UTF-8");
+        assert_eq!(info("ga"), "windows-1252");
+        assert!(LangProvider::in_dirs("eo", &config, dirs.clone()).in_place());
+        assert!(!LangProvider::in_dirs("ga", &config, dirs.clone()).in_place());
     }
 }
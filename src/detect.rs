@@ -0,0 +1,136 @@
+//! Language detection with character n-gram profiles.
+//!
+//! Profiles count the trigrams of the word lists of the `.dic` dictionaries,
+//! texts are given to the profile under which their trigrams are the most
+//! likely, with add-one smoothing.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::SpellError;
+
+/// Minimum number of letters needed to detect the language of a text.
+const MIN_LETTERS: usize = 12;
+
+/// Calls `f` with each trigram of the word, padded with `_` at both ends.
+fn trigrams<F: FnMut(String)>(word: &str, mut f: F) {
+    let chars: Vec<char> = format!("_{}_", word.to_lowercase()).chars().collect();
+    for gram in chars.windows(3) {
+        f(gram.iter().collect());
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+}
+
+/// The trigram frequencies of a language.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    lang: String,
+    counts: HashMap<String, u32>,
+    total: u64,
+}
+
+impl Profile {
+    /// Trains a profile from a list of words.
+    pub fn train<'a, I: IntoIterator<Item = &'a str>>(lang: &str, words: I) -> Profile {
+        let mut counts = HashMap::new();
+        let mut total = 0;
+        for word in words {
+            trigrams(word, |gram| {
+                *counts.entry(gram).or_insert(0) += 1;
+                total += 1;
+            });
+        }
+        Profile {
+            lang: lang.to_string(),
+            counts,
+            total,
+        }
+    }
+
+    /// Trains a profile from the words of a `.dic` dictionary.
+    pub fn from_dic<P: AsRef<Path>>(lang: &str, path: P) -> Result<Profile, SpellError> {
+        let raw = fs::read(path).map_err(SpellError::ReadDictionaryError)?;
+        let content = String::from_utf8_lossy(&raw);
+        let entries = content
+            .lines()
+            .skip(1)
+            .map(|line| line.split('/').next().unwrap().trim())
+            .filter(|word| !word.is_empty() && word.chars().all(char::is_alphabetic));
+        Ok(Self::train(lang, entries))
+    }
+
+    /// Language code of the profile.
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// Log-likelihood of the trigrams under this profile.
+    fn score(&self, sample: &HashMap<String, u32>) -> f64 {
+        let denominator = (self.total + self.counts.len() as u64 + 1) as f64;
+        sample
+            .iter()
+            .map(|(gram, n)| {
+                let count = self.counts.get(gram).copied().unwrap_or(0);
+                f64::from(*n) * ((f64::from(count) + 1.0) / denominator).ln()
+            })
+            .sum()
+    }
+}
+
+/// Detects the language of texts among a set of profiles.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    profiles: Vec<Profile>,
+}
+
+impl Detector {
+    /// Creates a detector choosing between the given profiles.
+    pub fn new(profiles: Vec<Profile>) -> Detector {
+        Detector { profiles }
+    }
+
+    /// Returns the language of the most likely profile, if the text is long
+    /// enough to tell.
+    pub fn detect(&self, text: &str) -> Option<&str> {
+        let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+        if letters < MIN_LETTERS {
+            return None;
+        }
+        let mut sample = HashMap::new();
+        for word in words(text) {
+            trigrams(word, |gram| *sample.entry(gram).or_insert(0) += 1);
+        }
+        self.profiles
+            .iter()
+            .map(|profile| (profile.score(&sample), profile))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, profile)| profile.lang())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        let detector = Detector::new(vec![
+            Profile::from_dic("en_US", "files/en_US.dic").unwrap(),
+            Profile::from_dic("fr", "files/fr.dic").unwrap(),
+        ]);
+        assert_eq!(
+            detector.detect("The quick brown fox jumps over the lazy dog."),
+            Some("en_US")
+        );
+        assert_eq!(
+            detector.detect("Les élèves étudient la littérature française à l'université."),
+            Some("fr")
+        );
+        assert_eq!(detector.detect("Hello"), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Locations of the configuration, data and cache of rspell.
+//!
+//! They default to the user directories of the platform and can be overridden
+//! with the `RSPELL_CONFIG`, `RSPELL_DATA_DIR` and `RSPELL_CACHE_DIR`
+//! environment variables.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+
+/// Environment variable overriding the location of the config file.
+pub(crate) const CONFIG_ENV: &str = "RSPELL_CONFIG";
+/// Environment variable overriding the data directory.
+pub(crate) const DATA_DIR_ENV: &str = "RSPELL_DATA_DIR";
+/// Environment variable overriding the cache directory.
+pub(crate) const CACHE_DIR_ENV: &str = "RSPELL_CACHE_DIR";
+
+fn project_dirs() -> ProjectDirs {
+    ProjectDirs::from("", "", "rspell").expect("get project directories")
+}
+
+fn var(name: &str) -> Option<OsString> {
+    env::var_os(name).filter(|value| !value.is_empty())
+}
+
+/// Location of the config file and whether it comes from the environment.
+pub(crate) fn config_path() -> (PathBuf, bool) {
+    match var(CONFIG_ENV) {
+        Some(path) => (PathBuf::from(path), true),
+        None => (project_dirs().config_dir().join("config.toml"), false),
+    }
+}
+
+/// The data and cache directories.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Dirs {
+    pub(crate) data: PathBuf,
+    pub(crate) cache: PathBuf,
+}
+
+impl Dirs {
+    /// The directories of the environment, the user ones otherwise.
+    pub(crate) fn from_env() -> Dirs {
+        Self::from_vars(var)
+    }
+
+    fn from_vars<F: Fn(&str) -> Option<OsString>>(var: F) -> Dirs {
+        let (data, cache) = (var(DATA_DIR_ENV), var(CACHE_DIR_ENV));
+        // the user directories are only looked up when needed
+        let project = if data.is_none() || cache.is_none() {
+            Some(project_dirs())
+        } else {
+            None
+        };
+        Dirs {
+            data: data
+                .map(PathBuf::from)
+                .unwrap_or_else(|| project.as_ref().unwrap().data_local_dir().to_owned()),
+            cache: cache
+                .map(PathBuf::from)
+                .unwrap_or_else(|| project.as_ref().unwrap().cache_dir().to_owned()),
+        }
+    }
+
+    /// Directories rooted at the given data directory, the cache directory
+    /// is the one of the environment or a `cache` directory in it.
+    pub(crate) fn in_data_dir(data: PathBuf) -> Dirs {
+        let cache = var(CACHE_DIR_ENV).map_or_else(|| data.join("cache"), PathBuf::from);
+        Dirs { data, cache }
+    }
+
+    /// Directory of the installed dictionaries.
+    pub(crate) fn dictionaries(&self) -> PathBuf {
+        self.data.join("dictionaries")
+    }
+
+    /// Directory of the personal dictionaries.
+    pub(crate) fn personal(&self) -> PathBuf {
+        self.data.join("personal")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides() {
+        let dirs = Dirs::from_vars(|name| match name {
+            DATA_DIR_ENV => Some(OsString::from("/srv/rspell")),
+            CACHE_DIR_ENV => Some(OsString::from("/tmp/rspell")),
+            _ => None,
+        });
+        assert_eq!(
+            dirs.dictionaries(),
+            PathBuf::from("/srv/rspell/dictionaries")
+        );
+        assert_eq!(dirs.personal(), PathBuf::from("/srv/rspell/personal"));
+        assert_eq!(dirs.cache, PathBuf::from("/tmp/rspell"));
+        let dirs = Dirs::from_vars(|name| match name {
+            DATA_DIR_ENV => Some(OsString::from("/srv/rspell")),
+            _ => None,
+        });
+        assert_eq!(dirs.cache, project_dirs().cache_dir());
+    }
+}
@@ -0,0 +1,346 @@
+//! Downloads of dictionary files, and checks of their content.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::data::partial_path;
+use crate::SpellError;
+use curl::easy::{Easy, List};
+use sha2::{Digest, Sha256};
+
+/// Settings of the downloads of dictionaries.
+pub(crate) struct Download {
+    connect_timeout: Duration,
+    timeout: Duration,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Default for Download {
+    fn default() -> Download {
+        Download {
+            connect_timeout: Duration::from_secs(15),
+            timeout: Duration::from_secs(300),
+            retries: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Validators of a downloaded file, sent back to only download it again
+/// when it changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Validators {
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Reads a validator from a response header line, if it is one.
+    fn read_header(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => return,
+        };
+        if value.is_empty() {
+            return;
+        }
+        if name.eq_ignore_ascii_case("etag") {
+            self.etag = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("last-modified") {
+            self.last_modified = Some(value.to_string());
+        }
+    }
+
+    fn request_headers(&self) -> Result<List, curl::Error> {
+        let mut headers = List::new();
+        if let Some(etag) = &self.etag {
+            headers.append(&format!("If-None-Match: {}", etag))?;
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.append(&format!("If-Modified-Since: {}", last_modified))?;
+        }
+        Ok(headers)
+    }
+}
+
+impl Download {
+    /// Downloads a file, retrying with an exponential backoff on transient
+    /// errors, see `transient()`.
+    ///
+    /// The file is written next to its destination then renamed, so that an
+    /// interrupted download never leaves a truncated file behind. With the
+    /// validators of a previous download, the request is conditional and
+    /// `None` is returned when the file did not change.
+    pub(crate) fn fetch(
+        &self,
+        url: &str,
+        dst: &Path,
+        previous: Option<&Validators>,
+    ) -> Result<Option<Validators>, SpellError> {
+        let part = partial_path(dst, "part");
+        let mut attempt = 0;
+        loop {
+            log::debug!("downloading file from {} (attempt {})", url, attempt + 1);
+            let result = self.fetch_once(url, &part, previous);
+            match result {
+                Ok(Some(validators)) => {
+                    fs::rename(&part, dst).map_err(SpellError::FileCachingError)?;
+                    return Ok(Some(validators));
+                }
+                Ok(None) => {
+                    log::debug!("{} not modified", url);
+                    let _ = fs::remove_file(&part);
+                    return Ok(None);
+                }
+                Err(err) => {
+                    let _ = fs::remove_file(&part);
+                    if !transient(&err) || attempt >= self.retries {
+                        return Err(err);
+                    }
+                    let delay = self.backoff * 2u32.pow(attempt);
+                    log::warn!("download failed ({:?}), retrying in {:?}", err, delay);
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn fetch_once(
+        &self,
+        url: &str,
+        part: &Path,
+        previous: Option<&Validators>,
+    ) -> Result<Option<Validators>, SpellError> {
+        let mut file = File::create(part).map_err(SpellError::FileCachingError)?;
+        let mut easy = Easy::new();
+        easy.url(url).map_err(SpellError::DownloadError)?;
+        easy.follow_location(true)
+            .map_err(SpellError::DownloadError)?;
+        easy.connect_timeout(self.connect_timeout)
+            .map_err(SpellError::DownloadError)?;
+        easy.timeout(self.timeout)
+            .map_err(SpellError::DownloadError)?;
+        if let Some(previous) = previous {
+            let headers = previous
+                .request_headers()
+                .map_err(SpellError::DownloadError)?;
+            easy.http_headers(headers)
+                .map_err(SpellError::DownloadError)?;
+        }
+        let mut validators = Validators::default();
+        let mut write_error = None;
+        let performed = {
+            let mut transfer = easy.transfer();
+            transfer
+                .header_function(|line| {
+                    // each response of a redirection starts with its status line
+                    if line.starts_with(b"HTTP/") {
+                        validators = Validators::default();
+                    } else {
+                        validators.read_header(line);
+                    }
+                    true
+                })
+                .map_err(SpellError::DownloadError)?;
+            transfer
+                .write_function(|data| match file.write_all(data) {
+                    Ok(()) => Ok(data.len()),
+                    Err(err) => {
+                        write_error = Some(err);
+                        Ok(0)
+                    }
+                })
+                .map_err(SpellError::DownloadError)?;
+            transfer.perform()
+        };
+        if let Some(err) = write_error {
+            return Err(SpellError::FileCachingError(err));
+        }
+        performed.map_err(SpellError::DownloadError)?;
+        // non HTTP transfers, e.g. file://, have no status
+        let status = easy.response_code().map_err(SpellError::DownloadError)?;
+        if status == 304 && previous.is_some() {
+            return Ok(None);
+        }
+        if status != 0 && !(200..300).contains(&status) {
+            return Err(SpellError::HttpStatusError {
+                url: url.to_string(),
+                status,
+            });
+        }
+        file.sync_all().map_err(SpellError::FileCachingError)?;
+        Ok(Some(validators))
+    }
+}
+
+/// Whether a download error may not happen again: timeouts, connection
+/// errors, server errors and rate limiting. Others, e.g. a malformed URL or
+/// a missing file, are permanent.
+fn transient(err: &SpellError) -> bool {
+    match err {
+        SpellError::DownloadError(err) => {
+            err.is_operation_timedout()
+                || err.is_couldnt_resolve_host()
+                || err.is_couldnt_resolve_proxy()
+                || err.is_couldnt_connect()
+                || err.is_ssl_connect_error()
+                || err.is_send_error()
+                || err.is_recv_error()
+                || err.is_got_nothing()
+                || err.is_partial_file()
+                || err.is_http2_stream_error()
+                || err.is_again()
+        }
+        SpellError::HttpStatusError { status, .. } => *status == 429 || *status >= 500,
+        _ => false,
+    }
+}
+
+/// Hexadecimal SHA-256 digest of the data.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Checks that the content looks like a dictionary file of the given kind,
+/// `aff` or `dic`, e.g. not an HTML error page.
+pub(crate) fn sanity_check(kind: &str, raw: &[u8]) -> Result<(), &'static str> {
+    const AFF_DIRECTIVES: &[&str] = &[
+        "SET",
+        "FLAG",
+        "TRY",
+        "KEY",
+        "PFX",
+        "SFX",
+        "REP",
+        "LANG",
+        "WORDCHARS",
+        "MAP",
+    ];
+    let text = String::from_utf8_lossy(raw);
+    let text = text.trim_start_matches('\u{feff}');
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let first = lines.clone().next().ok_or("empty file")?;
+    if first.starts_with('<') {
+        return Err("HTML content");
+    }
+    match kind {
+        "aff" => {
+            let directive = |line: &str| {
+                let name = line.split_whitespace().next().unwrap_or("");
+                AFF_DIRECTIVES.contains(&name)
+            };
+            if lines.any(directive) {
+                Ok(())
+            } else {
+                Err("no affix directive")
+            }
+        }
+        "dic" => match first.split_whitespace().next().map(str::parse::<u64>) {
+            Some(Ok(_)) => Ok(()),
+            _ => Err("no word count on the first line"),
+        },
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{serve, serve_headers, TempDir};
+    use std::net::TcpListener;
+
+    fn quick() -> Download {
+        Download {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+            retries: 2,
+            backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn download_retries() {
+        let (url, requests) = serve(vec![(503, "busy"), (200, "SET UTF-8\n")]);
+        let dir = TempDir::new("download-retries");
+        let dst = dir.join("retry.aff");
+        quick()
+            .fetch(&format!("{}/en_US.aff", url), &dst, None)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "SET UTF-8\n");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn download_not_found() {
+        let (url, requests) = serve(vec![(404, "<html>Not Found</html>")]);
+        let dir = TempDir::new("download-not-found");
+        let dst = dir.join("missing.aff");
+        match quick().fetch(&format!("{}/missing.aff", url), &dst, None) {
+            Err(SpellError::HttpStatusError { status: 404, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        // no partial file is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn conditional_download() {
+        let (url, requests) = serve_headers(vec![
+            (200, "ETag: \"v1\"\r\n", b"1\nword\n"),
+            (304, "", b""),
+        ]);
+        let dir = TempDir::new("download-conditional");
+        let dst = dir.join("conditional.dic");
+        let url = format!("{}/en_US.dic", url);
+        let validators = quick().fetch(&url, &dst, None).unwrap().unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(validators.last_modified, None);
+        assert_eq!(quick().fetch(&url, &dst, Some(&validators)).unwrap(), None);
+        assert!(requests.lock().unwrap()[1].contains("If-None-Match: \"v1\"\r\n"));
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "1\nword\n");
+    }
+
+    #[test]
+    fn sanity() {
+        assert!(sanity_check("dic", b"\xef\xbb\xbf3\nfoo\nbar/S\nbaz\n").is_ok());
+        assert!(sanity_check("dic", b"foo\nbar\n").is_err());
+        assert!(sanity_check("aff", b"TRY esianrtolcdugmphbyfvkwz\n").is_ok());
+        assert!(sanity_check("aff", b"Not Found\n").is_err());
+        assert!(sanity_check("aff", b"").is_err());
+    }
+
+    #[test]
+    fn download_network_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/closed.aff", listener.local_addr().unwrap());
+        drop(listener);
+        let dir = TempDir::new("download-network-error");
+        match quick().fetch(&url, &dir.join("closed.aff"), None) {
+            Err(SpellError::DownloadError(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // permanent errors are not retried
+        let slow = Download {
+            backoff: Duration::from_secs(60),
+            ..quick()
+        };
+        match slow.fetch(
+            "unknown://example.org/en_US.aff",
+            &dir.join("bad.aff"),
+            None,
+        ) {
+            Err(SpellError::DownloadError(err)) => assert!(!err.is_couldnt_connect()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Encodings of dictionary files, which hunspell declares with the `SET`
+//! directive of the `.aff` file.
+
+use crate::SpellError;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1251, WINDOWS_1252, WINDOWS_874};
+
+/// Finds the encoding of a `SET` label, hunspell names included.
+pub(crate) fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    let label = label.trim().to_ascii_lowercase();
+    match label.as_str() {
+        "microsoft-cp1251" => Some(WINDOWS_1251),
+        "tis620-2533" => Some(WINDOWS_874),
+        _ => Encoding::for_label(label.as_bytes()).or_else(|| {
+            // e.g. ISO8859-16, only known as ISO-8859-16
+            let number = label.strip_prefix("iso8859-")?;
+            Encoding::for_label(format!("iso-8859-{}", number).as_bytes())
+        }),
+    }
+}
+
+/// Detects the encoding of a dictionary from the content of its `.aff` file.
+///
+/// A byte order mark wins over the `SET` directive. Without both, the file is
+/// UTF-8 when valid and ISO-8859-1 otherwise, like hunspell does.
+pub(crate) fn detect_encoding(aff: &[u8]) -> Result<&'static Encoding, SpellError> {
+    if let Some((encoding, _)) = Encoding::for_bom(aff) {
+        return Ok(encoding);
+    }
+    for line in aff.split(|byte| *byte == b'\n') {
+        let line = String::from_utf8_lossy(line);
+        let mut fields = line.split_whitespace();
+        if fields.next() == Some("SET") {
+            let label = fields.next().unwrap_or("");
+            log::debug!("detected encoding: {}", label);
+            return encoding_for_label(label)
+                .ok_or_else(|| SpellError::UnknownEncodingError(label.to_string()));
+        }
+    }
+    if std::str::from_utf8(aff).is_ok() {
+        Ok(UTF_8)
+    } else {
+        Ok(WINDOWS_1252)
+    }
+}
+
+/// Converts the content of a dictionary file to UTF-8, the `SET` directive of
+/// `.aff` files included.
+///
+/// Returns the content and whether some bytes were not valid in the encoding
+/// and got replaced.
+pub(crate) fn convert(raw: &[u8], encoding: &'static Encoding, aff: bool) -> (String, bool) {
+    let (content, _, lossy) = encoding.decode(raw);
+    if !aff {
+        return (content.into_owned(), lossy);
+    }
+    let mut converted = String::with_capacity(content.len() + 10);
+    let mut set = false;
+    for line in content.split_inclusive('\n') {
+        if line.split_whitespace().next() == Some("SET") {
+            converted.push_str("SET UTF-8\n");
+            set = true;
+        } else {
+            converted.push_str(line);
+        }
+    }
+    if !set {
+        converted.insert_str(0, "SET UTF-8\n");
+    }
+    (converted, lossy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings() {
+        let detect = |aff: &[u8]| detect_encoding(aff).map(Encoding::name);
+        assert_eq!(detect(b"# comment\n  SET  UTF-8\r\n").unwrap(), "UTF-8");
+        assert_eq!(detect(b"SET microsoft-cp1251\n").unwrap(), "windows-1251");
+        assert_eq!(detect(b"SET ISO8859-16\n").unwrap(), "ISO-8859-16");
+        assert_eq!(detect(b"SET TIS620-2533\n").unwrap(), "windows-874");
+        assert_eq!(detect(b"\xef\xbb\xbfSET ISO8859-1\n").unwrap(), "UTF-8");
+        assert_eq!(detect(b"TRY \xe9\n").unwrap(), "windows-1252");
+        assert_eq!(detect("TRY é\n".as_bytes()).unwrap(), "UTF-8");
+        match detect(b"SET ISCII-DEVANAGARI\n") {
+            Err(SpellError::UnknownEncodingError(label)) => assert_eq!(label, "ISCII-DEVANAGARI"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let (aff, lossy) = convert(b"\xef\xbb\xbfSET UTF-8\nTRY \xc3\xa9\xff\n", UTF_8, true);
+        assert_eq!(aff, "SET UTF-8\nTRY é\u{fffd}\n");
+        assert!(lossy);
+        let (aff, lossy) = convert(b"TRY \xe9\n", WINDOWS_1252, true);
+        assert_eq!(aff, "SET UTF-8\nTRY é\n");
+        assert!(!lossy);
+        let (dic, _) = convert(b"1\n\xf1\xe5\xec\n", WINDOWS_1251, false);
+        assert_eq!(dic, "1\nсем\n");
+    }
+}
@@ -0,0 +1,348 @@
+//! Removal of the files rspell does not need anymore.
+//!
+//! Each use of an installed dictionary is recorded in a `{name}.used` file of
+//! the dictionaries directory, holding its date in seconds since the Unix
+//! epoch.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::data::{self, InstallInfo};
+use crate::dirs::Dirs;
+use crate::SpellError;
+
+/// Age after which files of the cache and temporary files are stale, younger
+/// ones may belong to an install in progress.
+const GRACE_PERIOD: u64 = 60 * 60;
+
+/// Extensions of the files of an installed dictionary, its lock file aside.
+const EXTENSIONS: &[&str] = &["aff", "dic", "meta.toml", "used"];
+
+/// Why a file is garbage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GarbageKind {
+    /// A file left in the cache directory, e.g. a partial download.
+    Cache,
+    /// A temporary file of an interrupted write.
+    Temporary,
+    /// A file of a dictionary whose `.aff` or `.dic` file is missing.
+    Orphan,
+    /// A file of a dictionary not used for the given number of days.
+    Unused,
+}
+
+impl fmt::Display for GarbageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GarbageKind::Cache => "cache",
+            GarbageKind::Temporary => "temporary",
+            GarbageKind::Orphan => "orphan",
+            GarbageKind::Unused => "unused",
+        })
+    }
+}
+
+/// A file removed, or to remove, by
+/// [collect_garbage()](fn.collect_garbage.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Garbage {
+    /// Location of the file.
+    pub path: PathBuf,
+    /// Why the file is garbage.
+    pub kind: GarbageKind,
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+fn used_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.used", name))
+}
+
+/// Records that the named dictionary is used now, at most once per grace
+/// period to spare writes.
+pub(crate) fn record_use(dir: &Path, name: &str) {
+    let path = used_path(dir, name);
+    let now = data::now();
+    let recent = fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+        .is_some_and(|used| now.saturating_sub(used) < GRACE_PERIOD);
+    if !recent {
+        if let Err(err) = fs::write(&path, now.to_string()) {
+            log::debug!("cannot record use of {}: {}", name, err);
+        }
+    }
+}
+
+/// Last use of the named dictionary, its install date when it was never used.
+fn last_use(dir: &Path, name: &str) -> u64 {
+    fs::read_to_string(used_path(dir, name))
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .or_else(|| {
+            InstallInfo::read(&InstallInfo::path(dir, name))
+                .map(|info| info.installed)
+                .filter(|installed| *installed > 0)
+        })
+        .or_else(|| data::modified(&dir.join(format!("{}.dic", name))))
+        .unwrap_or(0)
+}
+
+struct Collector {
+    now: u64,
+    dry_run: bool,
+    garbage: Vec<Garbage>,
+}
+
+impl Collector {
+    fn remove(&mut self, path: PathBuf, kind: GarbageKind) -> Result<(), SpellError> {
+        let size = match fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(SpellError::RemoveDicError(err)),
+        };
+        if !self.dry_run {
+            log::info!("removing {} file {}", kind, path.display());
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(SpellError::RemoveDicError(err))
+                }
+                _ => {}
+            }
+        }
+        self.garbage.push(Garbage { path, kind, size });
+        Ok(())
+    }
+
+    fn stale(&self, path: &Path) -> bool {
+        self.now.saturating_sub(data::modified(path).unwrap_or(0)) > GRACE_PERIOD
+    }
+
+    fn cache(&mut self, dir: &Path) -> Result<(), SpellError> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+        for path in paths {
+            if self.stale(&path) {
+                self.remove(path, GarbageKind::Cache)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the stale temporary files of a directory.
+    fn temporaries(&mut self, dir: &Path) -> Result<(), SpellError> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            if self.stale(&path) {
+                self.remove(path, GarbageKind::Temporary)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dictionaries(&mut self, dir: &Path, unused_for: Option<u64>) -> Result<(), SpellError> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+        self.temporaries(dir)?;
+        let mut names = BTreeSet::new();
+        for entry in entries.flatten() {
+            let file_name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            if file_name.ends_with(".tmp") {
+                continue;
+            } else if let Some(name) = file_name.strip_suffix(".lock") {
+                names.insert(name.to_string());
+            } else if let Some(ext) = EXTENSIONS
+                .iter()
+                .find(|ext| file_name.ends_with(&format!(".{}", ext)))
+            {
+                names.insert(file_name[..file_name.len() - ext.len() - 1].to_string());
+            }
+        }
+        for name in names {
+            let path = |ext: &str| dir.join(format!("{}.{}", name, ext));
+            let had_lock = path("lock").exists();
+            // a dry run writes nothing, not even a lock file
+            let lock = if self.dry_run {
+                None
+            } else {
+                Some(data::lock_dictionary(dir, &name)?)
+            };
+            let kind = if !(path("aff").exists() && path("dic").exists()) {
+                GarbageKind::Orphan
+            } else if unused_for
+                .is_some_and(|limit| self.now.saturating_sub(last_use(dir, &name)) > limit)
+            {
+                GarbageKind::Unused
+            } else {
+                continue;
+            };
+            for ext in EXTENSIONS {
+                self.remove(path(ext), kind)?;
+            }
+            // the lock file goes last, while it is held
+            if had_lock {
+                self.remove(path("lock"), kind)?;
+            } else if lock.is_some() {
+                let _ = fs::remove_file(path("lock"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn collect_in(
+    dirs: &Dirs,
+    unused_for: Option<u64>,
+    now: u64,
+    dry_run: bool,
+) -> Result<Vec<Garbage>, SpellError> {
+    let mut collector = Collector {
+        now,
+        dry_run,
+        garbage: Vec::new(),
+    };
+    collector.cache(&dirs.cache)?;
+    collector.dictionaries(&dirs.dictionaries(), unused_for)?;
+    collector.temporaries(&dirs.personal())?;
+    Ok(collector.garbage)
+}
+
+/// Removes the files rspell does not need anymore and returns them.
+///
+/// These are the stale files of the cache directory, the temporary files of
+/// interrupted installs and updates of personal dictionaries, and the files of incomplete dictionaries, lock files
+/// included. With
+/// `unused_days`, the dictionaries not used for that many days are removed
+/// too. With `dry_run`, the files are only reported.
+pub fn collect_garbage(
+    unused_days: Option<u64>,
+    dry_run: bool,
+) -> Result<Vec<Garbage>, SpellError> {
+    collect_in(
+        &Dirs::from_env(),
+        unused_days.map(|days| days.saturating_mul(24 * 60 * 60)),
+        data::now(),
+        dry_run,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn collect() {
+        let base = TempDir::new("gc");
+        let dirs = Dirs {
+            data: base.join("data"),
+            cache: base.join("cache"),
+        };
+        let dicts = dirs.dictionaries();
+        fs::create_dir_all(&dicts).unwrap();
+        fs::create_dir_all(&dirs.cache).unwrap();
+        let now = data::now();
+        let day = 24 * 60 * 60;
+        for name in &["fr", "en_US"] {
+            fs::write(dicts.join(format!("{}.aff", name)), "SET UTF-8\n").unwrap();
+            fs::write(dicts.join(format!("{}.dic", name)), "1\nword\n").unwrap();
+        }
+        record_use(&dicts, "en_US");
+        fs::write(used_path(&dicts, "fr"), (now - 10 * day).to_string()).unwrap();
+        fs::write(dicts.join("it_IT.aff"), "SET UTF-8\n").unwrap();
+        fs::write(InstallInfo::path(&dicts, "de"), "").unwrap();
+        fs::write(dicts.join("fr.aff.1234.tmp"), "").unwrap();
+        fs::write(dicts.join("fr.lock"), "").unwrap();
+        fs::write(dicts.join("nl.lock"), "").unwrap();
+        fs::write(dicts.join("notes.txt"), "").unwrap();
+        fs::write(dirs.cache.join("es.dic"), "1\npalabra\n").unwrap();
+        fs::create_dir_all(dirs.personal()).unwrap();
+        fs::write(dirs.personal().join("fr.txt"), "coucou\n").unwrap();
+        fs::write(dirs.personal().join("fr.txt.1234.0.tmp"), "").unwrap();
+
+        let report = |garbage: &[Garbage]| -> Vec<(String, GarbageKind)> {
+            garbage
+                .iter()
+                .map(|g| {
+                    let name = g.path.file_name().unwrap().to_string_lossy().to_string();
+                    (name, g.kind)
+                })
+                .collect()
+        };
+        // files are still fresh, only unused and orphan dictionaries go
+        let fresh = collect_in(&dirs, Some(7 * day), now, true).unwrap();
+        assert_eq!(
+            report(&fresh),
+            vec![
+                (String::from("de.meta.toml"), GarbageKind::Orphan),
+                (String::from("fr.aff"), GarbageKind::Unused),
+                (String::from("fr.dic"), GarbageKind::Unused),
+                (String::from("fr.used"), GarbageKind::Unused),
+                (String::from("fr.lock"), GarbageKind::Unused),
+                (String::from("it_IT.aff"), GarbageKind::Orphan),
+                (String::from("nl.lock"), GarbageKind::Orphan),
+            ]
+        );
+        assert!(dicts.join("fr.aff").exists() && !dicts.join("de.lock").exists());
+
+        let later = now + 2 * GRACE_PERIOD;
+        let garbage = collect_in(&dirs, None, later, false).unwrap();
+        assert_eq!(
+            report(&garbage),
+            vec![
+                (String::from("es.dic"), GarbageKind::Cache),
+                (String::from("fr.aff.1234.tmp"), GarbageKind::Temporary),
+                (String::from("de.meta.toml"), GarbageKind::Orphan),
+                (String::from("it_IT.aff"), GarbageKind::Orphan),
+                (String::from("nl.lock"), GarbageKind::Orphan),
+                (String::from("fr.txt.1234.0.tmp"), GarbageKind::Temporary),
+            ]
+        );
+        assert_eq!(garbage[0].size, 10);
+        assert!(dirs.personal().join("fr.txt").exists());
+        let mut left: Vec<String> = fs::read_dir(&dicts)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            vec![
+                "en_US.aff",
+                "en_US.dic",
+                "en_US.lock",
+                "en_US.used",
+                "fr.aff",
+                "fr.dic",
+                "fr.lock",
+                "fr.used",
+                "notes.txt",
+            ]
+        );
+    }
+}
@@ -202,7 +202,7 @@ mod tests {
     fn suggest() {
         let h = Hunspell::new("files/fr.aff", "files/fr.dic");
         let suggs = h.suggest("francais");
-        assert_eq!(suggs.get(0), Some(&"français".to_string()));
+        assert_eq!(suggs.first(), Some(&"français".to_string()));
     }
 
     #[test]
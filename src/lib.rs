@@ -1,30 +1,81 @@
+mod archive;
+mod builder;
+mod bundle;
 mod config;
 mod data;
+mod detect;
+mod dirs;
+mod download;
+mod encoding;
+mod gc;
 pub mod hunspell;
+mod locale;
+pub mod modes;
+mod personal;
+mod provenance;
+#[cfg(test)]
+mod testing;
+mod validate;
 
+use std::cell::OnceCell;
 use std::io;
 use std::path::PathBuf;
 
-pub use config::load_config;
-pub use data::LangProvider;
-use directories::ProjectDirs;
+pub use archive::{archive_dictionaries, install_archive, ArchiveDictionary};
+pub use builder::SpellBuilder;
+pub use bundle::{export_bundle, import_bundle};
+pub use config::{
+    config_file, find_project, load_config, load_project_config, Config, DictionariesConfig,
+    SourceConfig,
+};
+pub use data::{
+    installed_dictionaries, remote_dictionaries, system_dictionaries, DictionaryInfo, InstallInfo,
+    LangProvider, RemoteDictionary, SourceInfo,
+};
+pub use detect::{Detector, Profile};
+use dirs::Dirs;
+pub use gc::{collect_garbage, Garbage, GarbageKind};
+pub use locale::Locale;
+pub use personal::PersonalDictionary;
+use provenance::Location;
+pub use provenance::{FileProvenance, Origin, Provenance};
 use unicode_segmentation::UnicodeSegmentation;
-
-pub(crate) fn dirs() -> ProjectDirs {
-    ProjectDirs::from("", "", "rspell").expect("get project directories")
-}
+pub use validate::{ConfigIssue, Severity};
 
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum SpellError {
     DicNotFound(PathBuf),
     NoDicSource,
+    NoLanguage,
     FileCachingError(io::Error),
     RemoveDicError(io::Error),
     ConversionError(io::Error),
     InitConfigError(io::Error),
     ReadConfigError(io::Error),
     LoadConfigError(toml::de::Error),
+    ReadInputError(io::Error),
+    ReadArchiveError(zip::result::ZipError),
+    ParseDocumentError(roxmltree::Error),
+    ParseNotebookError(serde_json::Error),
+    PersonalDictionaryError(io::Error),
+    ReadDictionaryError(io::Error),
+    DownloadError(curl::Error),
+    HttpStatusError {
+        url: String,
+        status: u32,
+    },
+    ChecksumError {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    CorruptDicError {
+        url: String,
+        reason: String,
+    },
+    BundleError(io::Error),
+    UnknownEncodingError(String),
 }
 
 /// Result of a check. Wraps possible corrections when this is an incorrect result.
@@ -39,10 +90,7 @@ pub enum SpellResult {
 impl SpellResult {
     /// Returns `true` when it is a correct result, `false` otherwise.
     pub fn correct(&self) -> bool {
-        match self {
-            Self::Correct => true,
-            _ => false,
-        }
+        matches!(self, Self::Correct)
     }
 }
 
@@ -57,9 +105,37 @@ pub struct BadWord<'a> {
     pub suggestions: Vec<String>,
 }
 
+/// Returns the edit distance between two words, in characters.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+struct Dictionary {
+    lang: String,
+    aff: PathBuf,
+    dic: PathBuf,
+    location: Location,
+    hs: hunspell::Hunspell,
+    personal: PersonalDictionary,
+}
+
 /// This is the main structure. It allows to gather dictionaries for a given
 /// language and spellcheck individual word or whole text using it.
 ///
+/// Several languages can be used at once, a word is then correct when any of
+/// their dictionaries accepts it.
+///
 /// # Examples
 ///
 /// ```
@@ -74,9 +150,16 @@ pub struct BadWord<'a> {
 ///         bad.word, bad.offset, bad.suggestions
 ///     );
 /// }
+///
+/// let spell = rspell::Spell::new_multi(&["en_US", "fr"]).unwrap();
+///
+/// assert!(spell.check_word("color").correct());
+/// assert!(spell.check_word("couleur").correct());
 /// ```
 pub struct Spell {
-    hs: hunspell::Hunspell,
+    dicts: Vec<Dictionary>,
+    ignore: Vec<String>,
+    detector: OnceCell<Detector>,
 }
 
 impl Spell {
@@ -85,43 +168,294 @@ impl Spell {
     /// This function will also ensure that data files for the language are
     /// available. If not it will try to get it using a
     /// [LangProvider](struct.LangProvider.html).
+    ///
+    /// The global and language [personal dictionaries](struct.PersonalDictionary.html)
+    /// are loaded on top of it.
     pub fn new(lang: &str) -> Result<Spell, SpellError> {
+        Self::new_multi(&[lang])
+    }
+
+    /// Creates a new spellchecker for several languages, the first one is the
+    /// primary language.
+    ///
+    /// Fails with `NoLanguage` if `langs` is empty.
+    pub fn new_multi(langs: &[&str]) -> Result<Spell, SpellError> {
         if cfg!(feature = "local_files") {
-            let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("files");
-            Ok(Spell {
-                hs: hunspell::Hunspell::new(
-                    dir.join(&format!("{}.aff", lang)),
-                    dir.join(&format!("{}.dic", lang)),
-                ),
-            })
+            // the bundled files need no configuration
+            Self::with_dirs(langs, &Config::default(), &Dirs::from_env())
         } else {
-            let config = config::load_config()?;
-            let provider = LangProvider::new(lang, &config);
-            provider.ensure_data()?;
-            Ok(Spell {
-                hs: provider.into(),
+            Self::with_config_multi(langs, &config::load_config()?)
+        }
+    }
+
+    /// Creates a new spellchecker for the given language code with the given
+    /// configuration, e.g. one loaded by
+    /// [load_project_config()](fn.load_project_config.html).
+    ///
+    /// The words of the configuration are accepted as well as the ones
+    /// matching its ignore patterns.
+    pub fn with_config(lang: &str, config: &Config) -> Result<Spell, SpellError> {
+        Self::with_config_multi(&[lang], config)
+    }
+
+    /// Creates a new spellchecker for several languages with the given
+    /// configuration, the first one is the primary language.
+    ///
+    /// Fails with `NoLanguage` if `langs` is empty.
+    pub fn with_config_multi(langs: &[&str], config: &Config) -> Result<Spell, SpellError> {
+        Self::with_dirs(langs, config, &Dirs::from_env())
+    }
+
+    /// Creates a spellchecker with a builder, to choose the configuration and
+    /// the directories without relying on the user ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let data_dir = std::env::temp_dir().join("rspell-builder-doc");
+    /// // the user directories are relative to the home directory
+    /// let mut config = rspell::Config::default();
+    /// config.dictionaries.user_directories.clear();
+    /// let spell = rspell::Spell::builder()
+    ///     .lang("en_US")
+    ///     .config(config)
+    ///     .data_dir(data_dir)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert!(spell.check_word("color").correct());
+    /// ```
+    pub fn builder() -> SpellBuilder {
+        SpellBuilder::default()
+    }
+
+    pub(crate) fn with_dirs(
+        langs: &[&str],
+        config: &Config,
+        dirs: &Dirs,
+    ) -> Result<Spell, SpellError> {
+        if langs.is_empty() {
+            return Err(SpellError::NoLanguage);
+        }
+        let mut dicts = Vec::with_capacity(langs.len());
+        for lang in langs {
+            let (lang, files) = if cfg!(feature = "local_files") {
+                let (lang, aff, dic) = Self::local_files(lang);
+                (lang, (aff, dic, Location::Bundled))
+            } else {
+                let provider = LangProvider::in_dirs(lang, config, dirs.clone());
+                provider.ensure_data()?;
+                let location = if provider.in_place() {
+                    Location::System
+                } else {
+                    gc::record_use(&dirs.dictionaries(), provider.lang());
+                    Location::Installed
+                };
+                (
+                    provider.lang().to_owned(),
+                    (
+                        provider.aff().to_owned(),
+                        provider.dic().to_owned(),
+                        location,
+                    ),
+                )
+            };
+            dicts.push(Self::dictionary(&lang, files, dirs)?);
+        }
+        // paragraphs in another language are checked with its dictionary only
+        for dict in &dicts {
+            for word in &config.words {
+                dict.hs.add(word);
+            }
+        }
+        for dict in &dicts[1..] {
+            dicts[0].personal.load_into(&dict.hs)?;
+        }
+        Ok(Spell {
+            dicts,
+            ignore: config.ignore.clone(),
+            detector: OnceCell::new(),
+        })
+    }
+
+    fn local_files(lang: &str) -> (String, PathBuf, PathBuf) {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("files");
+        let lang = data::choose_dictionary(lang, std::iter::once(dir.as_path()), &[]);
+        let aff = dir.join(format!("{}.aff", lang));
+        let dic = dir.join(format!("{}.dic", lang));
+        (lang, aff, dic)
+    }
+
+    fn dictionary(
+        lang: &str,
+        (aff, dic, location): (PathBuf, PathBuf, Location),
+        dirs: &Dirs,
+    ) -> Result<Dictionary, SpellError> {
+        let hs = hunspell::Hunspell::new(&aff, &dic);
+        let personal = PersonalDictionary::in_dirs(dirs, lang);
+        PersonalDictionary::in_dirs(dirs, "global").load_into(&hs)?;
+        personal.load_into(&hs)?;
+        Ok(Dictionary {
+            lang: lang.to_string(),
+            aff,
+            dic,
+            location,
+            hs,
+            personal,
+        })
+    }
+
+    /// Names of the dictionaries actually used, the primary one first.
+    ///
+    /// They may differ from the requested language codes, see
+    /// [LangProvider::new](struct.LangProvider.html#method.new).
+    pub fn langs(&self) -> Vec<&str> {
+        self.dicts.iter().map(|dict| dict.lang.as_str()).collect()
+    }
+
+    /// Files of the dictionaries actually used, the primary one first, with
+    /// their origin and checksums.
+    ///
+    /// It helps to find out why a word is accepted on a machine but not on
+    /// another one.
+    pub fn provenance(&self) -> Result<Vec<Provenance>, SpellError> {
+        self.dicts
+            .iter()
+            .map(|dict| Provenance::of(&dict.lang, &dict.aff, &dict.dic, dict.location))
+            .collect()
+    }
+
+    /// Detects which of the languages the given text is written in.
+    ///
+    /// The language whose dictionary accepts the most words of the text wins,
+    /// the [n-gram profiles](struct.Detector.html) of the languages decide
+    /// between equally good ones. They are trained from the `.dic` files on
+    /// the first call. Returns `None` when the text is too short to tell.
+    pub fn detect(&self, text: &str) -> Result<Option<&str>, SpellError> {
+        if self.detector.get().is_none() {
+            let profiles = self
+                .dicts
+                .iter()
+                .map(|dict| Profile::from_dic(&dict.lang, &dict.dic))
+                .collect::<Result<_, _>>()?;
+            let _ = self.detector.set(Detector::new(profiles));
+        }
+        let guess = match self.detector.get().unwrap().detect(text) {
+            Some(guess) => guess,
+            None => return Ok(None),
+        };
+        let votes: Vec<usize> = self
+            .dicts
+            .iter()
+            .map(|dict| {
+                text.unicode_words()
+                    .filter(|word| dict.hs.spell(word))
+                    .count()
             })
+            .collect();
+        let best = votes.iter().copied().max().unwrap_or(0);
+        let mut winners = self
+            .dicts
+            .iter()
+            .zip(&votes)
+            .filter(|(_, &count)| count == best);
+        Ok(Some(match (winners.next(), winners.next()) {
+            (Some((dict, _)), None) => &dict.lang,
+            _ => guess,
+        }))
+    }
+
+    /// Adds a word to the personal dictionary of the primary language.
+    ///
+    /// It is accepted in all the languages, the personal dictionary of the
+    /// primary language is loaded into the other ones.
+    pub fn learn(&self, word: &str) -> Result<(), SpellError> {
+        self.dicts[0].personal.learn(word)?;
+        for dict in &self.dicts {
+            dict.hs.add(word);
         }
+        Ok(())
+    }
+
+    /// Adds a word to the personal dictionary of the primary language, with
+    /// the affixes of `example`, e.g. learning "rustacean" like "crustacean"
+    /// also accepts "rustaceans".
+    pub fn learn_like(&self, word: &str, example: &str) -> Result<(), SpellError> {
+        self.dicts[0].personal.learn_like(word, example)?;
+        for dict in &self.dicts {
+            personal::add_like(&dict.hs, word, example);
+        }
+        Ok(())
+    }
+
+    /// Rejects a word from now on, even if the dictionaries of the languages
+    /// contain it.
+    pub fn forget(&self, word: &str) -> Result<(), SpellError> {
+        for dict in &self.dicts {
+            dict.personal.forget(word)?;
+            dict.hs.remove(word);
+        }
+        Ok(())
     }
 
     /// Checks spelling for the given word.
+    ///
+    /// With several languages, the suggestions of all dictionaries are merged
+    /// and ranked by their distance to the word, then by their rank in each
+    /// dictionary.
     pub fn check_word(&self, word: &str) -> SpellResult {
-        if self.hs.spell(word) {
-            SpellResult::Correct
-        } else {
-            SpellResult::Incorrect {
-                suggestions: self.hs.suggest(word),
+        self.check_word_in(&self.dicts, word)
+    }
+
+    fn check_word_in(&self, dicts: &[Dictionary], word: &str) -> SpellResult {
+        let ignored = || {
+            self.ignore
+                .iter()
+                .any(|pattern| config::wildcard_match(pattern, word))
+        };
+        if dicts.iter().any(|dict| dict.hs.spell(word)) || ignored() {
+            return SpellResult::Correct;
+        }
+        if let [dict] = dicts {
+            return SpellResult::Incorrect {
+                suggestions: dict.hs.suggest(word),
+            };
+        }
+        let mut ranked: Vec<(usize, usize, String)> = dicts
+            .iter()
+            .flat_map(|dict| dict.hs.suggest(word).into_iter().enumerate())
+            .map(|(rank, suggestion)| (distance(word, &suggestion), rank, suggestion))
+            .collect();
+        ranked.sort_by_key(|(distance, rank, _)| (*distance, *rank));
+        let mut suggestions: Vec<String> = Vec::with_capacity(ranked.len());
+        for (_, _, suggestion) in ranked {
+            if !suggestions.contains(&suggestion) {
+                suggestions.push(suggestion);
             }
         }
+        SpellResult::Incorrect { suggestions }
     }
 
     /// Checks spelling for the given text.
     ///
     /// Word boundaries are determined using unicode segmentation rules.
     pub fn check<'a>(&self, text: &'a str) -> Vec<BadWord<'a>> {
+        self.check_in(&self.dicts, text)
+    }
+
+    /// Checks spelling for the given text with the dictionary of one of the
+    /// languages only.
+    pub(crate) fn check_lang<'a>(&self, lang: &str, text: &'a str) -> Vec<BadWord<'a>> {
+        match self.dicts.iter().position(|dict| dict.lang == lang) {
+            Some(i) => self.check_in(&self.dicts[i..=i], text),
+            None => self.check(text),
+        }
+    }
+
+    fn check_in<'a>(&self, dicts: &[Dictionary], text: &'a str) -> Vec<BadWord<'a>> {
         text.split_word_bound_indices()
             .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
-            .filter_map(|(i, word)| match self.check_word(word) {
+            .filter_map(|(i, word)| match self.check_word_in(dicts, word) {
                 SpellResult::Correct => None,
                 SpellResult::Incorrect { suggestions } => Some(BadWord {
                     offset: i,
@@ -132,3 +466,126 @@ impl Spell {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn edit_distance() {
+        assert_eq!(distance("color", "colour"), 1);
+        assert_eq!(distance("coleur", "couleur"), 1);
+        assert_eq!(distance("été", "ete"), 2);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn locale_fallback() {
+        let spell = Spell::new_multi(&["en-us", "fr_CA"]).unwrap();
+        assert_eq!(spell.langs(), vec!["en_US", "fr"]);
+        assert!(matches!(Spell::new_multi(&[]), Err(SpellError::NoLanguage)));
+    }
+
+    #[test]
+    fn multi() {
+        let spell = Spell::new_multi(&["en_US", "fr"]).unwrap();
+        assert_eq!(spell.langs(), vec!["en_US", "fr"]);
+        let bad: Vec<_> = spell
+            .check("The red couleur, la maison bleue, flg")
+            .into_iter()
+            .map(|bad| bad.word)
+            .collect();
+        assert_eq!(bad, vec!["flg"]);
+        match spell.check_word("coulour") {
+            SpellResult::Incorrect { suggestions } => {
+                assert!(suggestions.contains(&String::from("couleur")));
+                assert!(
+                    suggestions.contains(&String::from("colour"))
+                        || suggestions.contains(&String::from("color"))
+                );
+            }
+            SpellResult::Correct => panic!("coulour is not a word"),
+        }
+    }
+
+    #[test]
+    fn builder() {
+        let data_dir = TempDir::new("builder");
+        let config: Config = toml::from_str(
+            r#"
+            lang = "en_US"
+            words = ["rspell"]
+            [dictionaries]
+            directories = []
+            sources = {}
+            "#,
+        )
+        .unwrap();
+        let spell = Spell::builder()
+            .config(config)
+            .data_dir(data_dir.path())
+            .build()
+            .unwrap();
+        assert_eq!(spell.langs(), vec!["en_US"]);
+        assert!(spell.check_word("rspell").correct());
+        spell.learn("hunspell").unwrap();
+        let personal = std::fs::read_to_string(data_dir.join("personal/en_US.txt")).unwrap();
+        assert!(personal.lines().any(|word| word == "hunspell"));
+        let unset = Spell::builder()
+            .config(toml::from_str("").unwrap())
+            .data_dir(data_dir.path())
+            .build();
+        assert!(matches!(unset, Err(SpellError::NoLanguage)));
+    }
+
+    #[test]
+    fn secondary_words() {
+        let data_dir = TempDir::new("words");
+        let config: Config = toml::from_str(
+            r#"
+            words = ["rspell"]
+            [dictionaries]
+            directories = []
+            sources = {}
+            "#,
+        )
+        .unwrap();
+        let build = || {
+            Spell::builder()
+                .lang("en_US")
+                .lang("fr")
+                .config(config.clone())
+                .data_dir(data_dir.path())
+                .build()
+                .unwrap()
+        };
+        let doc = "# The colour of the house\n\nLa maison de rspell est bleue mais \
+                   la porte de hunspell est rouge.\n";
+        let spell = build();
+        spell.learn("hunspell").unwrap();
+        let bad: Vec<_> = spell
+            .check_detected(modes::Mode::Markdown, doc)
+            .unwrap()
+            .into_iter()
+            .map(|bad| (bad.lang, bad.bad.word))
+            .collect();
+        assert_eq!(bad, vec![(String::from("en_US"), "colour")]);
+        // learnt words are loaded into all the languages
+        let bad = build().check_detected(modes::Mode::Markdown, doc).unwrap();
+        assert_eq!(bad.len(), 1);
+    }
+
+    #[test]
+    fn provenance() {
+        let spell = Spell::new_multi(&["en_US", "fr"]).unwrap();
+        let provenance = spell.provenance().unwrap();
+        let langs: Vec<_> = provenance.iter().map(|p| p.lang.as_str()).collect();
+        assert_eq!(langs, spell.langs());
+        assert_eq!(provenance[0].aff.path, spell.dicts[0].aff);
+        assert_eq!(provenance[0].encoding, "UTF-8");
+        if cfg!(feature = "local_files") {
+            assert_eq!(provenance[1].dic.origin, Origin::Bundled);
+        }
+    }
+}
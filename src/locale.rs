@@ -0,0 +1,188 @@
+//! Language tags, in BCP 47 (`en-US`) or POSIX (`en_US.UTF-8`) form.
+
+use std::fmt;
+
+/// A parsed language tag.
+///
+/// # Examples
+///
+/// ```
+/// use rspell::Locale;
+///
+/// let locale = Locale::parse("fr-ca").unwrap();
+/// assert_eq!(locale.to_string(), "fr_CA");
+/// assert_eq!(locale.fallbacks(), vec!["fr_CA", "fr"]);
+///
+/// let available = vec![String::from("en_US"), String::from("fr_FR")];
+/// assert_eq!(locale.choose(&available), Some("fr_FR"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    /// Language subtag, lowercase, e.g. `fr`.
+    pub language: String,
+    /// Script subtag, titlecase, e.g. `Latn`.
+    pub script: Option<String>,
+    /// Region subtag, uppercase, e.g. `CA`.
+    pub region: Option<String>,
+    /// Other subtags, lowercase, e.g. `valencia`.
+    pub variants: Vec<String>,
+}
+
+impl Locale {
+    /// Parses a language tag, the POSIX encoding (`.UTF-8`) and modifier
+    /// (`@euro`) are ignored.
+    ///
+    /// Returns `None` when the tag does not start with a language subtag.
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let tag = tag.split(['.', '@']).next().unwrap();
+        let mut subtags = tag.split(['-', '_']);
+        let language = subtags.next()?;
+        if !(2..=8).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        let mut locale = Locale {
+            language: language.to_ascii_lowercase(),
+            script: None,
+            region: None,
+            variants: Vec::new(),
+        };
+        for subtag in subtags {
+            if subtag.is_empty() || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return None;
+            }
+            let alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+            if locale.script.is_none()
+                && locale.region.is_none()
+                && locale.variants.is_empty()
+                && subtag.len() == 4
+                && alphabetic
+            {
+                locale.script =
+                    Some(subtag[..1].to_ascii_uppercase() + &subtag[1..].to_ascii_lowercase());
+            } else if locale.region.is_none()
+                && locale.variants.is_empty()
+                && ((subtag.len() == 2 && alphabetic)
+                    || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit())))
+            {
+                locale.region = Some(subtag.to_ascii_uppercase());
+            } else {
+                locale.variants.push(subtag.to_ascii_lowercase());
+            }
+        }
+        Some(locale)
+    }
+
+    fn name(
+        language: &str,
+        script: Option<&str>,
+        region: Option<&str>,
+        variants: &[String],
+    ) -> String {
+        let mut name = language.to_string();
+        for subtag in script
+            .into_iter()
+            .chain(region)
+            .chain(variants.iter().map(String::as_str))
+        {
+            name.push('_');
+            name.push_str(subtag);
+        }
+        name
+    }
+
+    /// Names to look for, from the most specific to the least one, e.g.
+    /// `sr_Latn_RS`, `sr_Latn`, `sr_RS` then `sr`.
+    pub fn fallbacks(&self) -> Vec<String> {
+        let script = self.script.as_deref();
+        let region = self.region.as_deref();
+        let candidates = vec![
+            Self::name(&self.language, script, region, &self.variants),
+            Self::name(&self.language, script, region, &[]),
+            Self::name(&self.language, script, None, &[]),
+            Self::name(&self.language, None, region, &[]),
+            Self::name(&self.language, None, None, &[]),
+        ];
+        let mut fallbacks: Vec<String> = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            if !fallbacks.contains(&candidate) {
+                fallbacks.push(candidate);
+            }
+        }
+        fallbacks
+    }
+
+    /// Chooses the best of the available dictionary names.
+    ///
+    /// The [fallbacks](#method.fallbacks) are tried in order, then any
+    /// dictionary of the same language, the main region first, e.g. `fr_FR`
+    /// for `fr`. Names are compared once normalised, so `en-us` matches
+    /// `en_US`.
+    pub fn choose<'a>(&self, available: &'a [String]) -> Option<&'a str> {
+        let parsed: Vec<(&'a str, Locale)> = available
+            .iter()
+            .filter_map(|name| Locale::parse(name).map(|locale| (name.as_str(), locale)))
+            .collect();
+        for fallback in self.fallbacks() {
+            if let Some((name, _)) = parsed.iter().find(|(_, l)| l.to_string() == fallback) {
+                return Some(name);
+            }
+        }
+        let main_region = self.language.to_ascii_uppercase();
+        parsed
+            .iter()
+            .filter(|(_, locale)| locale.language == self.language)
+            .min_by_key(|(name, locale)| (locale.region.as_ref() != Some(&main_region), *name))
+            .map(|(name, _)| *name)
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Self::name(
+            &self.language,
+            self.script.as_deref(),
+            self.region.as_deref(),
+            &self.variants,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let normalized = |tag| Locale::parse(tag).map(|l| l.to_string());
+        assert_eq!(normalized("en-us").as_deref(), Some("en_US"));
+        assert_eq!(normalized("en_US.UTF-8").as_deref(), Some("en_US"));
+        assert_eq!(normalized("fr_FR@euro").as_deref(), Some("fr_FR"));
+        assert_eq!(normalized("sr-latn-rs").as_deref(), Some("sr_Latn_RS"));
+        assert_eq!(normalized("ca-valencia").as_deref(), Some("ca_valencia"));
+        assert_eq!(normalized("es-419").as_deref(), Some("es_419"));
+        assert_eq!(normalized("de_DE_frami").as_deref(), Some("de_DE_frami"));
+        assert_eq!(normalized("1er"), None);
+    }
+
+    #[test]
+    fn choose() {
+        let available = names(&["en_GB", "en_US", "fr", "fr_BE", "sr_LATN", "de_DE_frami"]);
+        let choose = |tag| Locale::parse(tag).unwrap().choose(&available);
+        assert_eq!(choose("en-us"), Some("en_US"));
+        assert_eq!(choose("fr_CA"), Some("fr"));
+        assert_eq!(choose("en"), Some("en_GB"));
+        assert_eq!(choose("sr-Latn-RS"), Some("sr_LATN"));
+        assert_eq!(choose("de"), Some("de_DE_frami"));
+        assert_eq!(choose("it_IT"), None);
+        let available = names(&["fr_BE", "fr_FR"]);
+        assert_eq!(
+            Locale::parse("fr").unwrap().choose(&available),
+            Some("fr_FR")
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! AsciiDoc mode.
+//!
+//! Attribute entries, comments, block attributes, block macros, listing,
+//! literal and passthrough blocks, monospace text, attribute references,
+//! cross-reference ids, inline macro targets and URLs are skipped.
+
+use super::{at_word_start, indent, lines, url_len, Chunk, Spans};
+
+/// Inline macros whose bracketed content is prose.
+const TEXT_MACROS: &[&str] = &["link", "mailto", "xref", "footnote"];
+/// Inline macros whose bracketed content is not prose.
+const OTHER_MACROS: &[&str] = &[
+    "anchor",
+    "asciimath",
+    "btn",
+    "footnoteref",
+    "icon",
+    "image",
+    "indexterm",
+    "kbd",
+    "latexmath",
+    "menu",
+    "pass",
+    "stem",
+];
+
+#[derive(Clone, Copy)]
+enum State<'a> {
+    Normal,
+    /// Skips everything up to the closing delimiter.
+    Delimited(&'a str),
+    /// Skips lines until a blank one.
+    SkipParagraph,
+    /// The next paragraph is verbatim, unless a delimited block follows.
+    VerbatimNext,
+}
+
+/// Whether the line delimits a block whose content is not prose.
+fn verbatim_delimiter(line: &str) -> bool {
+    line.starts_with("```")
+        || (line.len() >= 4
+            && ['-', '.', '+', '/']
+                .iter()
+                .any(|&d| line.chars().all(|c| c == d)))
+}
+
+/// Whether the line is a block macro such as `include::file[]`.
+fn block_macro(line: &str) -> bool {
+    match line.find("::") {
+        Some(i) => {
+            i > 0
+                && line[..i]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                && line.ends_with(']')
+        }
+        None => false,
+    }
+}
+
+/// Whether the line is an attribute entry such as `:toc: left`.
+fn attribute_entry(line: &str) -> bool {
+    line.starts_with(':')
+        && line[1..]
+            .find(':')
+            .is_some_and(|i| i > 0 && !line[1..=i].contains(' '))
+}
+
+pub(super) fn chunks(text: &str) -> Vec<Chunk<'_>> {
+    let mut out = Vec::new();
+    let mut state = State::Normal;
+    let mut continued = false;
+    let mut paragraph = false;
+    for (offset, line) in lines(text) {
+        let content = line.trim_end();
+        let blank = content.is_empty();
+        if continued {
+            continued = content.ends_with(" \\");
+            continue;
+        }
+        match state {
+            State::Delimited(delimiter) => {
+                if content == delimiter {
+                    state = State::Normal;
+                }
+                continue;
+            }
+            State::SkipParagraph if !blank => continue,
+            State::VerbatimNext if !blank && !verbatim_delimiter(content) => {
+                state = State::SkipParagraph;
+                continue;
+            }
+            _ => {}
+        }
+        if blank {
+            state = State::Normal;
+            paragraph = false;
+            continue;
+        }
+        if verbatim_delimiter(content) {
+            state = State::Delimited(content);
+        } else if content.starts_with("//") {
+            // comment line
+        } else if attribute_entry(content) {
+            continued = content.ends_with(" \\");
+        } else if content.starts_with('[') && content.ends_with(']') {
+            let style = content[1..].split([',', ']']).next();
+            if let Some("source") | Some("listing") | Some("literal") | Some("stem") = style {
+                state = State::VerbatimNext;
+            }
+        } else if block_macro(content) {
+            // include::, image::, ifdef:: and the like
+        } else if indent(content) > 0 && !paragraph {
+            // literal paragraph
+            state = State::SkipParagraph;
+        } else {
+            prose(&mut out, offset, content);
+            paragraph = true;
+        }
+    }
+    out
+}
+
+fn prose<'a>(out: &mut Vec<Chunk<'a>>, offset: usize, line: &'a str) {
+    let mut spans = Spans::new(out, offset, line);
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        let skip_to = if c == '`' {
+            rest[1..].find('`').map(|end| i + end + 2)
+        } else if c == '+' && at_word_start(line, i) {
+            let marks = rest.len() - rest.trim_start_matches('+').len();
+            rest[marks..]
+                .find(&rest[..marks])
+                .map(|end| i + 2 * marks + end)
+        } else if c == '{' {
+            rest.find('}')
+                .filter(|&end| {
+                    end > 1
+                        && rest[1..end]
+                            .chars()
+                            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                })
+                .map(|end| i + end + 1)
+        } else if rest.starts_with("[[") {
+            rest.find("]]").map(|end| i + end + 2)
+        } else if rest.starts_with("<<") {
+            rest.find(">>").map(|end| match rest[..end].find(',') {
+                // the text after the comma is prose
+                Some(comma) => {
+                    spans.skip(i, i + comma + 1);
+                    spans.keep(i + end);
+                    i + end + 2
+                }
+                None => i + end + 2,
+            })
+        } else if at_word_start(line, i) {
+            url_len(rest)
+                .map(|len| i + len)
+                .or_else(|| inline_macro(rest).map(|len| i + len))
+        } else {
+            None
+        };
+        match skip_to {
+            Some(end) => {
+                spans.skip(i, end);
+                i = end;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    spans.finish();
+}
+
+/// Length of the non-prose part of the inline macro starting the given text.
+fn inline_macro(s: &str) -> Option<usize> {
+    let colon = s.find(':')?;
+    let name = &s[..colon];
+    let text = TEXT_MACROS.contains(&name);
+    if !text && !OTHER_MACROS.contains(&name) {
+        return None;
+    }
+    let open = colon + s[colon..].find('[')?;
+    if s[colon..open].contains(char::is_whitespace) {
+        return None;
+    }
+    if text {
+        Some(open + 1)
+    } else {
+        s[open..].find(']').map(|end| open + end + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(doc: &str) -> String {
+        chunks(doc).into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn inline_markup() {
+        let doc = "Run `cargo tset` with {projct-name}, see <<sec-id,the sction>> and https://exmple.org[the site].";
+        assert_eq!(texts(doc), "Run  with , see the sction and [the site].");
+    }
+
+    #[test]
+    fn blocks() {
+        let doc = "\
+= Document
+:toc: lfet
+:description: a long \\
+  descripton
+
+// a coment
+
+[source,rust]
+----
+let wrod = 1;
+----
+
+[source,rust]
+let mian = 2;
+
+ indented litteral
+
+include::chaptr.adoc[]
+
+Some prose.
+";
+        let found = texts(doc);
+        for word in &[
+            "lfet",
+            "descripton",
+            "coment",
+            "wrod",
+            "mian",
+            "litteral",
+            "chaptr",
+        ] {
+            assert!(!found.contains(word), "{} found in {:?}", word, found);
+        }
+        assert!(found.contains("Some prose."));
+        assert!(found.contains("Document"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Source code mode, only the comments are checked.
+//!
+//! String and char literals are skipped so that comment markers or quotes
+//! inside them are not mistaken for comments or strings. Code spans and URLs within comments are skipped.
+
+use super::{url_len, Chunk, Spans};
+
+/// Comment syntax of a programming language.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Comments {
+    /// Line comments starting with `#`, e.g. Python, shell or Ruby.
+    Hash,
+    /// Line comments starting with `//` and block comments enclosed in `/*`
+    /// and `*/`, e.g. C, Rust or JavaScript.
+    Slash,
+}
+
+impl Comments {
+    /// Returns the comment syntax of the given language name, if known.
+    pub fn for_language(name: &str) -> Option<Comments> {
+        match name.to_lowercase().as_str() {
+            "python" | "py" | "shell" | "sh" | "bash" | "ruby" | "rb" | "r" | "perl" | "pl"
+            | "julia" | "jl" | "toml" | "yaml" | "yml" | "makefile" => Some(Comments::Hash),
+            "rust" | "rs" | "c" | "h" | "cpp" | "c++" | "cc" | "hpp" | "java" | "javascript"
+            | "js" | "typescript" | "ts" | "go" | "kotlin" | "kt" | "swift" | "scala"
+            | "csharp" | "cs" => Some(Comments::Slash),
+            _ => None,
+        }
+    }
+}
+
+/// Length of the char literal starting the given text, e.g. `'"'` or
+/// `'\''`, `None` for a Rust lifetime.
+fn char_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\\') => {
+            chars.next()?;
+            // escapes are short, e.g. '\u{1F600}'
+            chars
+                .take(10)
+                .take_while(|(_, c)| *c != '\n')
+                .find(|(_, c)| *c == '\'')
+                .map(|(i, _)| i + 1)
+        }
+        (_, '\n') | (_, '\'') => None,
+        _ => match chars.next()? {
+            (i, '\'') => Some(i + 1),
+            _ => None,
+        },
+    }
+}
+
+/// Length of the string or char literal starting the given text.
+fn string_len(s: &str, comments: Comments) -> Option<usize> {
+    let quote = match s.chars().next()? {
+        '"' => "\"",
+        '\'' if comments == Comments::Hash => "'",
+        '\'' => return char_len(s),
+        _ => return None,
+    };
+    let delimiter = if comments == Comments::Hash && s.starts_with(&quote.repeat(3)) {
+        quote.repeat(3)
+    } else {
+        quote.to_string()
+    };
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(delimiter.len()) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if s[i..].starts_with(delimiter.as_str()) {
+            return Some(i + delimiter.len());
+        } else if c == '\n' && delimiter.len() == 1 {
+            return Some(i);
+        }
+    }
+    Some(s.len())
+}
+
+pub(super) fn chunks(text: &str, comments: Comments) -> Vec<Chunk<'_>> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    // shebang lines are not comments
+    if text.starts_with("#!") {
+        i = text.find('\n').unwrap_or(text.len());
+    }
+    while i < text.len() {
+        let rest = &text[i..];
+        let (start, end, next) = match comments {
+            Comments::Hash if rest.starts_with('#') => {
+                (i + 1, i + line_end(rest), i + line_end(rest))
+            }
+            Comments::Slash if rest.starts_with("//") => {
+                (i + 2, i + line_end(rest), i + line_end(rest))
+            }
+            Comments::Slash if rest.starts_with("/*") => match rest.find("*/") {
+                Some(end) => (i + 2, i + end, i + end + 2),
+                None => (i + 2, text.len(), text.len()),
+            },
+            _ => {
+                i += string_len(rest, comments)
+                    .unwrap_or_else(|| rest.chars().next().unwrap().len_utf8());
+                continue;
+            }
+        };
+        comment(&mut out, start, &text[start..end]);
+        i = next;
+    }
+    out
+}
+
+fn line_end(s: &str) -> usize {
+    s.find('\n').unwrap_or(s.len())
+}
+
+fn comment<'a>(out: &mut Vec<Chunk<'a>>, offset: usize, text: &'a str) {
+    let mut spans = Spans::new(out, offset, text);
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+        let skip_to = if c == '`' {
+            rest[1..].find('`').map(|end| i + end + 2)
+        } else {
+            url_len(rest).map(|len| i + len)
+        };
+        match skip_to {
+            Some(end) => {
+                spans.skip(i, end);
+                i = end;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    spans.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(doc: &str, comments: Comments) -> Vec<&str> {
+        chunks(doc, comments).into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn hash_comments() {
+        let doc = "#!/usr/bin/env python\nx = \"# not a coment\"  # a real one\ny = '''\n# still a string\n'''\n";
+        assert_eq!(texts(doc, Comments::Hash), vec![" a real one"]);
+    }
+
+    #[test]
+    fn slash_comments() {
+        let doc = "let s = \"// nope\"; // uses `wrod`\n/* block\n   comment */ let y = 2;";
+        assert_eq!(
+            texts(doc, Comments::Slash),
+            vec![" uses ", " block\n   comment "]
+        );
+        let doc = "let q = '\"'; // a coment\nlet e = '\\''; fn f<'a>(s: &'a str) {} // lifetime\n";
+        assert_eq!(texts(doc, Comments::Slash), vec![" a coment", " lifetime"]);
+    }
+}
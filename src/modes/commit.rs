@@ -0,0 +1,158 @@
+//! Git commit message mode.
+//!
+//! Comment lines, the diff added by `git commit -v`, trailers such as
+//! `Signed-off-by:`, issue references, commit ids, code spans and URLs are
+//! skipped.
+//!
+//! It can be used as a `commit-msg` hook, in `.git/hooks/commit-msg`:
+//!
+//! ```sh
+//! #!/bin/sh
+//! exec rspell --mode commit "$1"
+//! ```
+
+use super::{at_word_start, lines, url_len, Chunk, Spans};
+
+/// Whether the line is a trailer such as `Signed-off-by: Someone`.
+fn trailer(line: &str) -> bool {
+    line.starts_with("(cherry picked from commit ")
+        || line.find(": ").is_some_and(|i| {
+            i > 0
+                && line[..i]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Whether the token is an issue reference or a commit id, e.g. `#12`,
+/// `owner/repo#12`, `GH-12`, `PROJ-12` or `1a2b3c4d`.
+fn reference(token: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if let Some(hash) = token.rfind('#') {
+        let repo = &token[..hash];
+        return digits(&token[hash + 1..])
+            && (repo.is_empty()
+                || repo.contains('/')
+                    && repo
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || "/-_.".contains(c)));
+    }
+    if let Some(dash) = token.rfind('-') {
+        let key = &token[..dash];
+        if digits(&token[dash + 1..])
+            && key.starts_with(|c: char| c.is_ascii_uppercase())
+            && key
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            return true;
+        }
+    }
+    (7..=40).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_hexdigit())
+        && token.chars().any(|c| c.is_ascii_digit())
+}
+
+pub(super) fn chunks(text: &str) -> Vec<Chunk<'_>> {
+    let mut kept = Vec::new();
+    for (offset, line) in lines(text) {
+        if (line.starts_with('#') && line.contains(" >8 ")) || line.starts_with("diff --git ") {
+            break;
+        }
+        if !line.starts_with('#') {
+            kept.push((offset, line));
+        }
+    }
+    while kept.last().is_some_and(|(_, line)| line.trim().is_empty()) {
+        kept.pop();
+    }
+    // the last paragraph is made of trailers, unless it is the only one
+    let last_paragraph = kept
+        .iter()
+        .rposition(|(_, line)| line.trim().is_empty())
+        .map_or(kept.len(), |i| i + 1);
+    if last_paragraph > 0
+        && kept[last_paragraph..]
+            .iter()
+            .all(|(_, line)| trailer(line) || line.starts_with(char::is_whitespace))
+    {
+        kept.truncate(last_paragraph);
+    }
+    let mut out = Vec::new();
+    for (offset, line) in kept {
+        prose(&mut out, offset, line);
+    }
+    out
+}
+
+fn prose<'a>(out: &mut Vec<Chunk<'a>>, offset: usize, line: &'a str) {
+    let mut spans = Spans::new(out, offset, line);
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        let skip_to = if !at_word_start(line, i) {
+            None
+        } else if c == '`' {
+            rest[1..].find('`').map(|end| i + end + 2)
+        } else if let Some(len) = url_len(rest) {
+            Some(i + len)
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || ",;:()[]{}<>\"'".contains(c))
+                .unwrap_or(rest.len());
+            let token = rest[..end].trim_end_matches(['.', '!', '?']);
+            if reference(token) {
+                Some(i + token.len())
+            } else {
+                None
+            }
+        };
+        match skip_to {
+            Some(end) => {
+                spans.skip(i, end);
+                i = end;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    spans.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(doc: &str) -> String {
+        chunks(doc).into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn message() {
+        let doc = "\
+Fix the parsre for GH-12 and owner/repo#3
+
+Reverts 1a2b3c4d, see #42 and PROJ-7.
+
+Signed-off-by: Sonmeone <someone@example.org>
+Co-authored-by: Othr <other@example.org>
+# Please enter the commit message for your changes. Lines starting
+# with '#' will be ignored.
+# ------------------------ >8 ------------------------
+diff --git a/src/lib.rs b/src/lib.rs
++let wrod = 1;
+";
+        assert_eq!(texts(doc), "Fix the parsre for  and Reverts , see  and .");
+    }
+
+    #[test]
+    fn subject_only() {
+        assert_eq!(texts("Docs: fix a typo\n"), "Docs: fix a typo");
+    }
+
+    #[test]
+    fn verbose_diff() {
+        let doc = "Subject\n\ndiff --git a/file b/file\n+mistaek\n";
+        assert_eq!(texts(doc), "Subject");
+    }
+}
@@ -0,0 +1,430 @@
+//! Email mode, for RFC 5322 messages and mbox files.
+//!
+//! The subject and the `text/plain` bodies are checked once decoded, other
+//! headers, attachments, quoted lines and signatures are skipped.
+
+use std::path::Path;
+
+use super::{lines, url_len, Chunk, Position, Spans};
+use crate::{BadWord, Spell};
+use encoding_rs::{Encoding, UTF_8};
+
+/// A message of a mailbox.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// Index of the message in the mailbox, 0-based.
+    pub index: usize,
+    /// Decoded subject, if any.
+    pub subject: Option<String>,
+    /// Decoded `text/plain` bodies.
+    pub bodies: Vec<String>,
+}
+
+/// Location of a misspelt word in a message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    /// The word is in the subject.
+    Subject,
+    /// The word is in a body.
+    Body {
+        /// Index of the body in the message, 0-based.
+        part: usize,
+        /// Position of the word in the decoded body.
+        position: Position,
+    },
+}
+
+/// Represents a misspelt word in a mailbox.
+#[derive(Debug)]
+pub struct EmailBadWord<'a> {
+    /// Index of the message containing the word, 0-based.
+    pub message: usize,
+    /// Location of the word in the message.
+    pub location: Location,
+    /// The misspelt word, its offset is relative to the subject or body.
+    pub bad: BadWord<'a>,
+}
+
+/// Whether the path looks like a message or a mailbox, based on its extension.
+pub fn is_email<P: AsRef<Path>>(path: P) -> bool {
+    match path.as_ref().extension().and_then(|e| e.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("eml") || ext.eq_ignore_ascii_case("mbox"),
+        None => false,
+    }
+}
+
+/// Reads the messages of a mailbox in the mbox format, or of a single message.
+pub fn read_messages(raw: &[u8]) -> Vec<Message> {
+    split_mbox(raw)
+        .into_iter()
+        .enumerate()
+        .map(|(index, raw)| {
+            let (headers, body) = split_headers(&raw);
+            let mut bodies = Vec::new();
+            collect_bodies(&headers, body, &mut bodies);
+            Message {
+                index,
+                subject: header(&headers, "subject").map(|s| decode_words(&s)),
+                bodies,
+            }
+        })
+        .collect()
+}
+
+/// Checks spelling for the given messages.
+pub fn check<'a>(spell: &Spell, messages: &'a [Message]) -> Vec<EmailBadWord<'a>> {
+    let mut bad_words = Vec::new();
+    for message in messages {
+        if let Some(subject) = &message.subject {
+            for bad in spell.check(subject) {
+                bad_words.push(EmailBadWord {
+                    message: message.index,
+                    location: Location::Subject,
+                    bad,
+                });
+            }
+        }
+        for (part, body) in message.bodies.iter().enumerate() {
+            for chunk in body_chunks(body) {
+                for bad in spell.check(chunk.text) {
+                    let offset = chunk.offset + bad.offset;
+                    bad_words.push(EmailBadWord {
+                        message: message.index,
+                        location: Location::Body {
+                            part,
+                            position: Position::of(body, offset),
+                        },
+                        bad: BadWord { offset, ..bad },
+                    });
+                }
+            }
+        }
+    }
+    bad_words
+}
+
+/// Extracts the prose of a body, without quoted lines and signature.
+fn body_chunks(body: &str) -> Vec<Chunk<'_>> {
+    let mut out = Vec::new();
+    for (offset, line) in lines(body) {
+        // the signature separator of RFC 3676, with its trailing space
+        if line == "-- " {
+            break;
+        }
+        if line.trim_start().starts_with('>') {
+            continue;
+        }
+        let mut spans = Spans::new(&mut out, offset, line);
+        let mut i = 0;
+        while i < line.len() {
+            match url_len(&line[i..]) {
+                Some(len) => {
+                    spans.skip(i, i + len);
+                    i += len;
+                }
+                None => i += line[i..].chars().next().unwrap().len_utf8(),
+            }
+        }
+        spans.finish();
+    }
+    out
+}
+
+/// Splits a mbox into messages, a lone message is returned as is.
+fn split_mbox(raw: &[u8]) -> Vec<Vec<u8>> {
+    if !raw.starts_with(b"From ") {
+        return vec![raw.to_vec()];
+    }
+    let mut messages: Vec<Vec<u8>> = Vec::new();
+    let mut previous_blank = true;
+    for line in raw.split_inclusive(|&b| b == b'\n') {
+        if previous_blank && line.starts_with(b"From ") {
+            messages.push(Vec::new());
+        } else if let Some(current) = messages.last_mut() {
+            // mboxrd escaping of lines starting with "From "
+            let unquoted = line.iter().position(|&b| b != b'>').unwrap_or(0);
+            if unquoted > 0 && line[unquoted..].starts_with(b"From ") {
+                current.extend_from_slice(&line[1..]);
+            } else {
+                current.extend_from_slice(line);
+            }
+        }
+        previous_blank = line == b"\n" || line == b"\r\n";
+    }
+    messages
+}
+
+/// Splits a message into its unfolded headers and its body.
+fn split_headers(raw: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut offset = 0;
+    for line in raw.split_inclusive(|&b| b == b'\n') {
+        offset += line.len();
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            return (headers, &raw[offset..]);
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push_str(line);
+            }
+        } else if let Some(colon) = line.find(':') {
+            headers.push((
+                line[..colon].trim().to_lowercase(),
+                line[colon + 1..].trim().to_string(),
+            ));
+        }
+    }
+    (headers, &raw[raw.len()..])
+}
+
+fn header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.clone())
+}
+
+/// Parses a header value like `text/plain; charset="utf-8"`.
+fn parse_params(value: &str) -> (String, Vec<(String, String)>) {
+    let mut items = value.split(';');
+    let main = items.next().unwrap_or("").trim().to_lowercase();
+    let params = items
+        .filter_map(|item| {
+            let eq = item.find('=')?;
+            Some((
+                item[..eq].trim().to_lowercase(),
+                item[eq + 1..].trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect();
+    (main, params)
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn collect_bodies(headers: &[(String, String)], body: &[u8], bodies: &mut Vec<String>) {
+    let (content_type, params) =
+        parse_params(&header(headers, "content-type").unwrap_or_else(|| "text/plain".into()));
+    let (disposition, _) =
+        parse_params(&header(headers, "content-disposition").unwrap_or_default());
+    if disposition == "attachment" {
+        return;
+    }
+    if content_type.starts_with("multipart/") {
+        if let Some(boundary) = param(&params, "boundary") {
+            for part in split_multipart(body, boundary) {
+                let (part_headers, part_body) = split_headers(part);
+                collect_bodies(&part_headers, part_body, bodies);
+            }
+        }
+    } else if content_type == "text/plain" {
+        let encoding = header(headers, "content-transfer-encoding")
+            .unwrap_or_default()
+            .to_lowercase();
+        let decoded = match encoding.as_str() {
+            "quoted-printable" => decode_quoted_printable(body, false),
+            "base64" => match base64::decode(
+                body.iter()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .copied()
+                    .collect::<Vec<u8>>(),
+            ) {
+                Ok(decoded) => decoded,
+                Err(err) => {
+                    log::warn!("skipping invalid base64 body: {}", err);
+                    return;
+                }
+            },
+            _ => body.to_vec(),
+        };
+        bodies.push(decode_charset(param(&params, "charset"), &decoded));
+    }
+}
+
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for line in body.split_inclusive(|&b| b == b'\n') {
+        let trimmed = String::from_utf8_lossy(line);
+        let trimmed = trimmed.trim_end();
+        if trimmed.starts_with(&delimiter) {
+            if let Some(start) = start {
+                parts.push(&body[start..offset]);
+            }
+            if trimmed[delimiter.len()..].starts_with("--") {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+fn decode_charset(charset: Option<&str>, raw: &[u8]) -> String {
+    let encoding = charset
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let (decoded, _, malformed) = encoding.decode(raw);
+    if malformed {
+        log::warn!("malformed {} text", encoding.name());
+    }
+    decoded.into_owned()
+}
+
+fn hex(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+fn decode_quoted_printable(raw: &[u8], header: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        match raw[i] {
+            b'=' if raw[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'=' if raw[i + 1..].starts_with(b"\n") => i += 2,
+            b'=' if i + 2 < raw.len() => match (hex(raw[i + 1]), hex(raw[i + 2])) {
+                (Some(high), Some(low)) => {
+                    out.push(high << 4 | low);
+                    i += 3;
+                }
+                _ => {
+                    out.push(b'=');
+                    i += 1;
+                }
+            },
+            b'_' if header => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Decodes the RFC 2047 encoded words of a header value.
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let decoded = rest[start + 2..].splitn(4, '?').collect::<Vec<_>>();
+        let word = match decoded.as_slice() {
+            [charset, encoding, text, tail] if tail.starts_with('=') => {
+                let raw = match encoding.to_ascii_uppercase().as_str() {
+                    "B" => base64::decode(text).ok(),
+                    "Q" => Some(decode_quoted_printable(text.as_bytes(), true)),
+                    _ => None,
+                };
+                raw.map(|raw| {
+                    let len = charset.len() + encoding.len() + text.len() + 6;
+                    (decode_charset(Some(charset), &raw), len)
+                })
+            }
+            _ => None,
+        };
+        match word {
+            Some((word, len)) => {
+                // whitespace between encoded words is not displayed
+                let between = &rest[..start];
+                if !(after_word && between.trim().is_empty()) {
+                    out.push_str(between);
+                }
+                out.push_str(&word);
+                rest = &rest[start + len..];
+                after_word = true;
+            }
+            None => {
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_message() {
+        let raw = b"From: someone@example.org\r
+Subject: =?UTF-8?Q?Caf=C3=A9_menu?= =?UTF-8?B?IHRvZGF5?=\r
+Content-Type: multipart/mixed; boundary=\"sep\"\r
+\r
+preamble\r
+--sep\r
+Content-Type: text/plain; charset=utf-8\r
+Content-Transfer-Encoding: quoted-printable\r
+\r
+Soft line br=\r
+eak and caf=C3=A9.\r
+--sep\r
+Content-Type: text/plain\r
+Content-Disposition: attachment; filename=notes.txt\r
+\r
+attached\r
+--sep\r
+Content-Type: text/plain; charset=iso-8859-1\r
+Content-Transfer-Encoding: base64\r
+\r
+bm/rbA==\r
+--sep--\r
+";
+        let messages = read_messages(raw);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].subject.as_deref(), Some("Café menu today"));
+        assert_eq!(
+            messages[0].bodies,
+            vec!["Soft line break and café.\r\n", "noël"]
+        );
+    }
+
+    #[test]
+    fn mbox() {
+        let raw = b"From a@example.org Mon Jan  1 00:00:00 2024
+Subject: First
+
+Hello
+>From the start
+
+From b@example.org Mon Jan  1 00:00:00 2024
+Subject: Second
+
+World
+";
+        let messages = read_messages(raw);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].bodies, vec!["Hello\nFrom the start\n\n"]);
+        assert_eq!(messages[1].subject.as_deref(), Some("Second"));
+    }
+
+    #[test]
+    fn quotes_and_signature() {
+        let body = "Some reply.\n> quoted txet\nSee https://exmple.org\n-- \nSignatur";
+        let found: String = body_chunks(body).into_iter().map(|c| c.text).collect();
+        assert_eq!(found, "Some reply.See ");
+        let body = "A list:\n--\nmore txet\r\n-- \r\nSignatur";
+        let found: Vec<&str> = body_chunks(body).into_iter().map(|c| c.text).collect();
+        assert_eq!(found, vec!["A list:", "--", "more txet"]);
+    }
+}
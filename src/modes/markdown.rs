@@ -0,0 +1,216 @@
+//! Markdown mode.
+//!
+//! Front matter, code blocks, code spans, HTML, link destinations, reference
+//! definitions and URLs are skipped. Link texts and image descriptions are kept.
+
+use super::{indent, lines, url_len, Chunk, Spans};
+
+#[derive(Clone, Copy)]
+enum State<'a> {
+    Normal,
+    /// Skips everything up to the closing fence.
+    Fenced(&'a str),
+    /// Skips everything up to the end of the front matter.
+    FrontMatter,
+    /// Skips everything up to the end of the HTML comment.
+    Comment,
+}
+
+/// Returns the fence opening a code block, if any.
+fn fence(line: &str) -> Option<&str> {
+    let content = line.trim_start();
+    let c = content.chars().next()?;
+    if c != '`' && c != '~' {
+        return None;
+    }
+    let len = content.len() - content.trim_start_matches(c).len();
+    if len >= 3 {
+        Some(&content[..len])
+    } else {
+        None
+    }
+}
+
+/// Whether the line starts a list item.
+fn list_item(line: &str) -> bool {
+    let content = line.trim_start();
+    ["- ", "* ", "+ "].iter().any(|m| content.starts_with(m))
+        || content
+            .find(". ")
+            .is_some_and(|i| i > 0 && content[..i].chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether the line is a link reference definition, e.g. `[id]: https://...`.
+fn reference_definition(line: &str) -> bool {
+    let content = line.trim_start();
+    content.starts_with('[')
+        && content
+            .find("]:")
+            .is_some_and(|i| !content[..i].contains(']'))
+}
+
+pub(super) fn chunks(text: &str) -> Vec<Chunk<'_>> {
+    let mut out = Vec::new();
+    let mut state = State::Normal;
+    let mut previous_blank = true;
+    let mut in_list = false;
+    let mut in_code = false;
+    for (i, (offset, line)) in lines(text).enumerate() {
+        let blank = line.trim().is_empty();
+        let mut code = false;
+        match state {
+            State::Fenced(opening) => {
+                if fence(line).is_some_and(|f| f.starts_with(opening)) {
+                    state = State::Normal;
+                }
+                continue;
+            }
+            State::FrontMatter => {
+                if line == "---" || line == "..." {
+                    state = State::Normal;
+                }
+                continue;
+            }
+            State::Comment => {
+                if line.contains("-->") {
+                    state = State::Normal;
+                }
+                continue;
+            }
+            State::Normal => {}
+        }
+        if i == 0 && line == "---" {
+            state = State::FrontMatter;
+        } else if let Some(opening) = fence(line) {
+            state = State::Fenced(opening);
+        } else if line.trim_start().starts_with("<!--") {
+            if !line.contains("-->") {
+                state = State::Comment;
+            }
+        } else if blank || reference_definition(line) {
+            // nothing to check
+        } else if indent(line) >= 4 && (previous_blank || in_code) && !in_list {
+            // indented code block, skipped line by line
+            code = true;
+        } else {
+            in_list = list_item(line) || (in_list && (indent(line) > 0 || !previous_blank));
+            prose(&mut out, offset, line);
+        }
+        previous_blank = blank;
+        // blank lines do not end a code block
+        in_code = code || (in_code && blank);
+    }
+    out
+}
+
+/// Index of the bracket closing the one opening the text.
+fn closing_bracket(s: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+fn prose<'a>(out: &mut Vec<Chunk<'a>>, offset: usize, line: &'a str) {
+    let mut spans = Spans::new(out, offset, line);
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        let skip_to = if c == '`' {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            rest[ticks..]
+                .find(&rest[..ticks])
+                .map(|end| i + 2 * ticks + end)
+        } else if c == '<' {
+            // HTML tags and autolinks
+            rest.find('>')
+                .filter(|&end| {
+                    end > 1 && !rest[1..end].starts_with(char::is_whitespace) && {
+                        let first = rest[1..].chars().next().unwrap();
+                        first.is_alphabetic() || first == '/' || first == '!'
+                    }
+                })
+                .map(|end| i + end + 1)
+        } else if c == ']' && (rest.starts_with("](") || rest.starts_with("][")) {
+            // link destinations and references, the text before is prose
+            let (open, close) = if rest.starts_with("](") {
+                ('(', ')')
+            } else {
+                ('[', ']')
+            };
+            closing_bracket(&rest[1..], open, close).map(|end| i + end + 2)
+        } else {
+            url_len(rest).map(|len| i + len)
+        };
+        match skip_to {
+            Some(end) => {
+                spans.skip(i, end);
+                i = end;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    spans.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(doc: &str) -> String {
+        chunks(doc).into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn inline_markup() {
+        let doc =
+            "Call `chekc()` or see [the manul](https://exmple.org/pge) and <span>html</span>.";
+        assert_eq!(texts(doc), "Call  or see [the manul and html.");
+    }
+
+    #[test]
+    fn blocks() {
+        let doc = "\
+---
+titel: Front matter
+---
+
+# Heading
+
+```rust
+let wrod = 1;
+```
+
+    let mian = 2;
+
+<!-- a coment
+over lines -->
+
+[ref]: https://exmple.org/pth
+
+- a list item
+    continued with indentaton
+";
+        let found = texts(doc);
+        for word in &["titel", "wrod", "mian", "coment", "exmple"] {
+            assert!(!found.contains(word), "{} found in {:?}", word, found);
+        }
+        assert!(found.contains("Heading"));
+        assert!(found.contains("indentaton"));
+    }
+
+    #[test]
+    fn indented_code() {
+        let doc = "Intro:\n\n    let wrod = 1;\n\n    let mian = 2;\n    let fo = 3;\nSome txet\n    lazy continuaton\n";
+        assert_eq!(texts(doc), "Intro:Some txet    lazy continuaton");
+    }
+}
@@ -0,0 +1,315 @@
+//! Input modes, used to check only the prose parts of structured documents.
+
+mod asciidoc;
+mod code;
+mod commit;
+pub mod email;
+mod markdown;
+pub mod notebook;
+pub mod office;
+mod rst;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{BadWord, Spell, SpellError};
+pub use code::Comments;
+
+/// A span of prose extracted from a document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chunk<'a> {
+    /// Offset from the document beginning, 0-based.
+    pub offset: usize,
+    /// The extracted text.
+    pub text: &'a str,
+}
+
+/// Format of a checked document, it determines which parts of it are prose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Mode {
+    /// Plain text, everything is checked.
+    Text,
+    /// reStructuredText, as used by Sphinx.
+    ReStructuredText,
+    /// AsciiDoc.
+    AsciiDoc,
+    /// Markdown, including the CommonMark and GitHub flavours.
+    Markdown,
+    /// Source code, only the comments are checked.
+    Code(Comments),
+    /// Git commit message, as found in `.git/COMMIT_EDITMSG`.
+    CommitMessage,
+}
+
+impl Mode {
+    /// Guesses the mode from the extension of the given path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Mode> {
+        if let Some("COMMIT_EDITMSG")
+        | Some("MERGE_MSG")
+        | Some("SQUASH_MSG")
+        | Some("TAG_EDITMSG") = path.as_ref().file_name().and_then(|n| n.to_str())
+        {
+            return Some(Mode::CommitMessage);
+        }
+        let ext = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "txt" | "text" => Some(Mode::Text),
+            "rst" | "rest" => Some(Mode::ReStructuredText),
+            "adoc" | "asciidoc" | "asc" => Some(Mode::AsciiDoc),
+            "md" | "markdown" => Some(Mode::Markdown),
+            ext => Comments::for_language(ext).map(Mode::Code),
+        }
+    }
+
+    /// Extracts the prose parts of the given document.
+    pub fn chunks(self, text: &str) -> Vec<Chunk<'_>> {
+        match self {
+            Mode::Text => vec![Chunk { offset: 0, text }],
+            Mode::ReStructuredText => rst::chunks(text),
+            Mode::AsciiDoc => asciidoc::chunks(text),
+            Mode::Markdown => markdown::chunks(text),
+            Mode::Code(comments) => code::chunks(text, comments),
+            Mode::CommitMessage => commit::chunks(text),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s {
+            "text" => Ok(Mode::Text),
+            "rst" => Ok(Mode::ReStructuredText),
+            "asciidoc" | "adoc" => Ok(Mode::AsciiDoc),
+            "markdown" | "md" => Ok(Mode::Markdown),
+            "commit" => Ok(Mode::CommitMessage),
+            _ => Comments::for_language(s)
+                .map(Mode::Code)
+                .ok_or_else(|| format!("unknown mode: {}", s)),
+        }
+    }
+}
+
+/// Position of an offset in a text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    /// Line number, 1-based.
+    pub line: usize,
+    /// Column in characters, 1-based.
+    pub column: usize,
+}
+
+impl Position {
+    /// Computes the position of the given byte offset in the text.
+    pub fn of(text: &str, offset: usize) -> Position {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// Represents a misspelt word in a document with its detected language.
+#[derive(Debug)]
+pub struct DetectedBadWord<'a> {
+    /// Language detected for the paragraph containing the word.
+    pub lang: String,
+    /// The misspelt word, its offset is relative to the whole document.
+    pub bad: BadWord<'a>,
+}
+
+impl Spell {
+    /// Checks spelling for the prose parts of the given document, each
+    /// paragraph with the dictionary of its detected language.
+    ///
+    /// Paragraphs are separated by blank lines. Those too short to detect
+    /// their language use the language of the previous one, the primary
+    /// language at first.
+    pub fn check_detected<'a>(
+        &self,
+        mode: Mode,
+        text: &'a str,
+    ) -> Result<Vec<DetectedBadWord<'a>>, SpellError> {
+        let breaks: Vec<usize> = lines(text)
+            .filter(|(_, line)| line.trim().is_empty())
+            .map(|(offset, _)| offset)
+            .collect();
+        let mut paragraphs: Vec<Vec<Chunk<'a>>> = Vec::new();
+        let mut current = None;
+        for chunk in mode.chunks(text) {
+            let paragraph = breaks.partition_point(|&offset| offset < chunk.offset);
+            if current != Some(paragraph) {
+                paragraphs.push(Vec::new());
+                current = Some(paragraph);
+            }
+            paragraphs.last_mut().unwrap().push(chunk);
+        }
+        let mut lang = self.langs()[0];
+        let mut out = Vec::new();
+        for chunks in paragraphs {
+            let sample: Vec<&str> = chunks.iter().map(|chunk| chunk.text).collect();
+            if let Some(detected) = self.detect(&sample.join(" "))? {
+                lang = detected;
+            }
+            for chunk in chunks {
+                out.extend(self.check_lang(lang, chunk.text).into_iter().map(|bad| {
+                    DetectedBadWord {
+                        lang: lang.to_string(),
+                        bad: BadWord {
+                            offset: chunk.offset + bad.offset,
+                            ..bad
+                        },
+                    }
+                }));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Checks spelling for the prose parts of the given document.
+    ///
+    /// Offsets of the returned words are relative to the whole document, use
+    /// [Position::of](modes/struct.Position.html#method.of) to get lines.
+    pub fn check_document<'a>(&self, mode: Mode, text: &'a str) -> Vec<BadWord<'a>> {
+        mode.chunks(text)
+            .into_iter()
+            .flat_map(|chunk| {
+                self.check(chunk.text).into_iter().map(move |bad| BadWord {
+                    offset: chunk.offset + bad.offset,
+                    ..bad
+                })
+            })
+            .collect()
+    }
+}
+
+/// Iterates over the lines of a text with their offsets, without line endings.
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, line.strip_suffix('\r').unwrap_or(line)))
+    })
+}
+
+/// Number of leading whitespace characters of a line.
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Returns the length of the URL starting the given text, if any.
+fn url_len(s: &str) -> Option<usize> {
+    const SCHEMES: &[&str] = &["http://", "https://", "ftp://", "file://", "mailto:"];
+    if SCHEMES.iter().any(|scheme| s.starts_with(scheme)) {
+        Some(
+            s.find(|c: char| c.is_whitespace() || c == '[' || c == '<' || c == '>')
+                .unwrap_or(s.len()),
+        )
+    } else {
+        None
+    }
+}
+
+/// Collects prose spans of a line while some inline markup is skipped.
+struct Spans<'a, 'b> {
+    out: &'b mut Vec<Chunk<'a>>,
+    offset: usize,
+    line: &'a str,
+    start: usize,
+}
+
+impl<'a, 'b> Spans<'a, 'b> {
+    fn new(out: &'b mut Vec<Chunk<'a>>, offset: usize, line: &'a str) -> Spans<'a, 'b> {
+        Spans {
+            out,
+            offset,
+            line,
+            start: 0,
+        }
+    }
+
+    /// Keeps the prose up to `from` and resumes after `to`.
+    fn skip(&mut self, from: usize, to: usize) {
+        self.keep(from);
+        self.start = to;
+    }
+
+    /// Keeps the prose up to `end`.
+    fn keep(&mut self, end: usize) {
+        if end > self.start {
+            self.out.push(Chunk {
+                offset: self.offset + self.start,
+                text: &self.line[self.start..end],
+            });
+            self.start = end;
+        }
+    }
+
+    fn finish(mut self) {
+        let end = self.line.len();
+        self.keep(end);
+    }
+}
+
+/// Whether the character before `i` allows inline markup to start at `i`.
+fn at_word_start(line: &str, i: usize) -> bool {
+    line[..i]
+        .chars()
+        .next_back()
+        .is_none_or(|c| !c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let text = "first\nsecond lïne\r\nthird";
+        assert_eq!(Position::of(text, 0), Position { line: 1, column: 1 });
+        assert_eq!(Position::of(text, 13), Position { line: 2, column: 8 });
+        assert_eq!(Position::of(text, 20), Position { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn detected() {
+        let spell = Spell::new_multi(&["en_US", "fr"]).unwrap();
+        let doc = "# The colour of teh house\n\nLa maison est bleue mais la porte est ruoge.\n";
+        let bad: Vec<_> = spell
+            .check_detected(Mode::Markdown, doc)
+            .unwrap()
+            .into_iter()
+            .map(|bad| (bad.lang, bad.bad.word))
+            .collect();
+        assert_eq!(
+            bad,
+            vec![
+                (String::from("en_US"), "colour"),
+                (String::from("en_US"), "teh"),
+                (String::from("fr"), "ruoge"),
+            ]
+        );
+    }
+
+    #[test]
+    fn mode_from_path() {
+        assert_eq!(
+            Mode::from_path("docs/index.rst"),
+            Some(Mode::ReStructuredText)
+        );
+        assert_eq!(Mode::from_path("README.adoc"), Some(Mode::AsciiDoc));
+        assert_eq!(
+            Mode::from_path("main.rs"),
+            Some(Mode::Code(Comments::Slash))
+        );
+        assert_eq!(
+            Mode::from_path(".git/COMMIT_EDITMSG"),
+            Some(Mode::CommitMessage)
+        );
+        assert_eq!(Mode::from_path("Makefile"), None);
+    }
+}
@@ -0,0 +1,189 @@
+//! Jupyter notebooks mode, for `.ipynb` files.
+//!
+//! Markdown cells are checked as Markdown and code cells as source code in the
+//! notebook language, outputs and raw cells are ignored.
+
+use std::path::Path;
+
+use super::{Comments, Mode, Position};
+use crate::{BadWord, Spell, SpellError};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct RawNotebook {
+    cells: Vec<RawCell>,
+    #[serde(default)]
+    metadata: RawMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct RawMetadata {
+    kernelspec: Option<RawKernelspec>,
+    language_info: Option<RawLanguageInfo>,
+}
+
+#[derive(Deserialize)]
+struct RawKernelspec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawLanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawCell {
+    cell_type: String,
+    #[serde(default)]
+    source: RawSource,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSource {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+impl Default for RawSource {
+    fn default() -> RawSource {
+        RawSource::Text(String::new())
+    }
+}
+
+/// A cell of a notebook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    /// Index of the cell in the notebook, 0-based.
+    pub index: usize,
+    /// Mode used to check the cell.
+    pub mode: Mode,
+    /// Content of the cell.
+    pub source: String,
+}
+
+/// Represents a misspelt word in a notebook.
+#[derive(Debug)]
+pub struct NotebookBadWord<'a> {
+    /// Index of the cell containing the word, 0-based.
+    pub cell: usize,
+    /// Position of the word in the cell.
+    pub position: Position,
+    /// The misspelt word, its offset is relative to the cell.
+    pub bad: BadWord<'a>,
+}
+
+/// Whether the path looks like a notebook, based on its extension.
+pub fn is_notebook<P: AsRef<Path>>(path: P) -> bool {
+    match path.as_ref().extension().and_then(|e| e.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("ipynb"),
+        None => false,
+    }
+}
+
+/// Reads the cells to check from the JSON content of a notebook.
+///
+/// Code cells are skipped when the comment syntax of the notebook language is
+/// unknown.
+pub fn read_cells(text: &str) -> Result<Vec<Cell>, SpellError> {
+    let notebook: RawNotebook =
+        serde_json::from_str(text).map_err(SpellError::ParseNotebookError)?;
+    let RawMetadata {
+        kernelspec,
+        language_info,
+    } = notebook.metadata;
+    let language = language_info
+        .and_then(|info| info.name)
+        .or_else(|| kernelspec.and_then(|spec| spec.language))
+        .unwrap_or_else(|| String::from("python"));
+    let code = Comments::for_language(&language).map(Mode::Code);
+    Ok(notebook
+        .cells
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, cell)| {
+            let mode = match cell.cell_type.as_str() {
+                "markdown" => Mode::Markdown,
+                "code" => code?,
+                _ => return None,
+            };
+            let source = match cell.source {
+                RawSource::Lines(lines) => lines.concat(),
+                RawSource::Text(text) => text,
+            };
+            Some(Cell {
+                index,
+                mode,
+                source,
+            })
+        })
+        .collect())
+}
+
+/// Checks spelling for the given cells.
+pub fn check<'a>(spell: &Spell, cells: &'a [Cell]) -> Vec<NotebookBadWord<'a>> {
+    cells
+        .iter()
+        .flat_map(|cell| {
+            spell
+                .check_document(cell.mode, &cell.source)
+                .into_iter()
+                .map(move |bad| NotebookBadWord {
+                    cell: cell.index,
+                    position: Position::of(&cell.source, bad.offset),
+                    bad,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells() {
+        let notebook = r##"{
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": ["# Title\n", "Some `code`\n"]},
+                {"cell_type": "raw", "metadata": {}, "source": "raw"},
+                {
+                    "cell_type": "code",
+                    "metadata": {},
+                    "execution_count": 1,
+                    "source": "x = 1  # a comment",
+                    "outputs": [{"output_type": "stream", "name": "stdout", "text": ["ignored\n"]}]
+                }
+            ],
+            "metadata": {"language_info": {"name": "python"}},
+            "nbformat": 4,
+            "nbformat_minor": 4
+        }"##;
+        let cells = read_cells(notebook).unwrap();
+        assert_eq!(
+            cells,
+            vec![
+                Cell {
+                    index: 0,
+                    mode: Mode::Markdown,
+                    source: String::from("# Title\nSome `code`\n"),
+                },
+                Cell {
+                    index: 2,
+                    mode: Mode::Code(Comments::Hash),
+                    source: String::from("x = 1  # a comment"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_language() {
+        let notebook = r#"{
+            "cells": [{"cell_type": "code", "source": "x"}],
+            "metadata": {"kernelspec": {"language": "cobol"}}
+        }"#;
+        assert!(read_cells(notebook).unwrap().is_empty());
+    }
+}
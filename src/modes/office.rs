@@ -0,0 +1,554 @@
+//! Office documents mode, for `.docx` and `.odt` files.
+//!
+//! Both formats are zip containers holding XML, the text of the paragraphs is
+//! extracted in reading order along with the language of each part of it.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::{BadWord, Spell, SpellError};
+use roxmltree::{Document, Node};
+use zip::ZipArchive;
+
+const W_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const OFFICE_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:office:1.0";
+const TEXT_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:text:1.0";
+const STYLE_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:style:1.0";
+const FO_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0";
+
+/// Language tag used for text that must not be checked.
+const NO_LANG: &str = "zxx";
+
+/// A part of a paragraph written in a given language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LangSpan {
+    /// Offset of the part in the paragraph text.
+    pub start: usize,
+    /// Offset of the end of the part in the paragraph text.
+    pub end: usize,
+    /// Language code of the part, if tagged.
+    pub lang: Option<String>,
+}
+
+/// A paragraph of an office document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paragraph {
+    /// Index of the paragraph in the document, 0-based.
+    pub index: usize,
+    /// Text of the paragraph.
+    pub text: String,
+    /// Language parts of the text, in order.
+    pub spans: Vec<LangSpan>,
+}
+
+impl Paragraph {
+    fn new(index: usize) -> Paragraph {
+        Paragraph {
+            index,
+            text: String::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, text: &str, lang: Option<&str>) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        let end = self.text.len();
+        match self.spans.last_mut() {
+            Some(last) if last.lang.as_deref() == lang => last.end = end,
+            _ => self.spans.push(LangSpan {
+                start,
+                end,
+                lang: lang.map(String::from),
+            }),
+        }
+    }
+
+    /// Returns the text surrounding the word at the given offset.
+    pub fn excerpt(&self, offset: usize, len: usize) -> &str {
+        const CONTEXT: usize = 30;
+        let mut start = offset.saturating_sub(CONTEXT);
+        while !self.text.is_char_boundary(start) {
+            start += 1;
+        }
+        let mut end = (offset + len + CONTEXT).min(self.text.len());
+        while !self.text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.text[start..end].trim()
+    }
+}
+
+/// Represents a misspelt word in an office document.
+#[derive(Debug)]
+pub struct OfficeBadWord<'a> {
+    /// Index of the paragraph containing the word, 0-based.
+    pub paragraph: usize,
+    /// Language code of the text containing the word, if tagged.
+    pub lang: Option<&'a str>,
+    /// Text surrounding the word.
+    pub excerpt: &'a str,
+    /// The misspelt word, its offset is relative to the paragraph.
+    pub bad: BadWord<'a>,
+}
+
+/// Whether the path looks like an office document, based on its extension.
+pub fn is_office<P: AsRef<Path>>(path: P) -> bool {
+    match path.as_ref().extension().and_then(|e| e.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("docx") || ext.eq_ignore_ascii_case("odt"),
+        None => false,
+    }
+}
+
+/// Reads the paragraphs of a `.docx` or `.odt` document.
+pub fn read_paragraphs<P: AsRef<Path>>(path: P) -> Result<Vec<Paragraph>, SpellError> {
+    let file = File::open(path).map_err(SpellError::ReadInputError)?;
+    read_archive(file)
+}
+
+fn read_archive<R: Read + Seek>(reader: R) -> Result<Vec<Paragraph>, SpellError> {
+    let mut archive = ZipArchive::new(reader).map_err(SpellError::ReadArchiveError)?;
+    if let Some(document) = read_member(&mut archive, "word/document.xml")? {
+        let styles = read_member(&mut archive, "word/styles.xml")?;
+        docx(&document, styles.as_deref())
+    } else {
+        let content = read_member(&mut archive, "content.xml")?.ok_or(
+            SpellError::ReadArchiveError(zip::result::ZipError::FileNotFound),
+        )?;
+        let styles = read_member(&mut archive, "styles.xml")?;
+        odt(&content, styles.as_deref())
+    }
+}
+
+fn read_member<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<String>, SpellError> {
+    match archive.by_name(name) {
+        Ok(mut member) => {
+            let mut content = String::new();
+            member
+                .read_to_string(&mut content)
+                .map_err(SpellError::ReadInputError)?;
+            Ok(Some(content))
+        }
+        Err(zip::result::ZipError::FileNotFound) => Ok(None),
+        Err(err) => Err(SpellError::ReadArchiveError(err)),
+    }
+}
+
+/// Returns the distinct language codes used in the paragraphs.
+pub fn languages(paragraphs: &[Paragraph]) -> Vec<&str> {
+    let mut langs: Vec<&str> = paragraphs
+        .iter()
+        .flat_map(|p| p.spans.iter().filter_map(|s| s.lang.as_deref()))
+        .filter(|&lang| lang != NO_LANG)
+        .collect();
+    langs.sort_unstable();
+    langs.dedup();
+    langs
+}
+
+/// Checks spelling for the given paragraphs.
+///
+/// The dictionary of each part of the text is given by `spell_for` from its
+/// language code, parts without a dictionary are skipped.
+pub fn check<'a, 's, F>(paragraphs: &'a [Paragraph], mut spell_for: F) -> Vec<OfficeBadWord<'a>>
+where
+    F: FnMut(Option<&str>) -> Option<&'s Spell>,
+{
+    let mut bad_words = Vec::new();
+    for paragraph in paragraphs {
+        for span in &paragraph.spans {
+            let lang = span.lang.as_deref();
+            if lang == Some(NO_LANG) {
+                continue;
+            }
+            let spell = match spell_for(lang) {
+                Some(spell) => spell,
+                None => continue,
+            };
+            for bad in spell.check(&paragraph.text[span.start..span.end]) {
+                let offset = span.start + bad.offset;
+                bad_words.push(OfficeBadWord {
+                    paragraph: paragraph.index,
+                    lang,
+                    excerpt: paragraph.excerpt(offset, bad.word.len()),
+                    bad: BadWord { offset, ..bad },
+                });
+            }
+        }
+    }
+    bad_words
+}
+
+/// Converts a language tag to a language code, e.g. `en-US` to `en_US`.
+fn lang_code(tag: &str) -> String {
+    tag.replace('-', "_")
+}
+
+fn is(node: &Node, ns: &str, name: &str) -> bool {
+    node.is_element() && node.tag_name().namespace() == Some(ns) && node.tag_name().name() == name
+}
+
+fn child<'a, 'input>(node: &Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| is(n, ns, name))
+}
+
+fn parse(xml: &str) -> Result<Document<'_>, SpellError> {
+    Document::parse(xml).map_err(SpellError::ParseDocumentError)
+}
+
+/// Language properties of text, unset when inherited.
+#[derive(Clone, Default)]
+struct TextProps {
+    lang: Option<String>,
+    /// Whether the text must not be checked.
+    no_proof: Option<bool>,
+}
+
+impl TextProps {
+    /// Fills the unset properties from the inherited ones.
+    fn or(self, inherited: TextProps) -> TextProps {
+        TextProps {
+            lang: self.lang.or(inherited.lang),
+            no_proof: self.no_proof.or(inherited.no_proof),
+        }
+    }
+
+    /// Language code of the text, `zxx` when it must not be checked.
+    fn lang(self) -> Option<String> {
+        if self.no_proof == Some(true) {
+            Some(String::from(NO_LANG))
+        } else {
+            self.lang
+        }
+    }
+}
+
+/// Properties and parents of the styles of a document, by name.
+#[derive(Default)]
+struct Styles {
+    styles: HashMap<String, (TextProps, Option<String>)>,
+    /// Properties of the text without style.
+    default: TextProps,
+    /// Style of the DOCX paragraphs without style.
+    default_paragraph: Option<String>,
+}
+
+impl Styles {
+    fn load_docx(&mut self, doc: &Document) {
+        for node in doc.descendants() {
+            if is(&node, W_NS, "rPrDefault") {
+                self.default = docx_props(child(&node, W_NS, "rPr"));
+            } else if is(&node, W_NS, "style") {
+                if let Some(id) = node.attribute((W_NS, "styleId")) {
+                    if node.attribute((W_NS, "type")) == Some("paragraph")
+                        && matches!(node.attribute((W_NS, "default")), Some("1") | Some("true"))
+                    {
+                        self.default_paragraph = Some(String::from(id));
+                    }
+                    let props = docx_props(child(&node, W_NS, "rPr"));
+                    let parent = docx_val(&node, "basedOn");
+                    self.styles
+                        .insert(String::from(id), (props, parent.map(String::from)));
+                }
+            }
+        }
+    }
+
+    fn load_odt(&mut self, doc: &Document) {
+        for node in doc.descendants() {
+            let lang = child(&node, STYLE_NS, "text-properties").and_then(|props| {
+                let language = props.attribute((FO_NS, "language"))?;
+                Some(match props.attribute((FO_NS, "country")) {
+                    Some(country) if country != "none" => format!("{}_{}", language, country),
+                    _ if language == "none" => String::from(NO_LANG),
+                    _ => String::from(language),
+                })
+            });
+            let props = TextProps {
+                lang,
+                no_proof: None,
+            };
+            if is(&node, STYLE_NS, "default-style")
+                && node.attribute((STYLE_NS, "family")) == Some("paragraph")
+            {
+                self.default = props;
+            } else if is(&node, STYLE_NS, "style") {
+                if let Some(name) = node.attribute((STYLE_NS, "name")) {
+                    let parent = node.attribute((STYLE_NS, "parent-style-name"));
+                    self.styles
+                        .insert(String::from(name), (props, parent.map(String::from)));
+                }
+            }
+        }
+    }
+
+    /// Properties of a style, inherited from its parents when unset.
+    fn props(&self, name: Option<&str>) -> TextProps {
+        let mut props = TextProps::default();
+        let mut name = name;
+        // bounded to protect against style cycles
+        for _ in 0..16 {
+            match name.and_then(|name| self.styles.get(name)) {
+                Some((own, parent)) => {
+                    props = props.or(own.clone());
+                    name = parent.as_deref();
+                }
+                None => break,
+            }
+        }
+        props
+    }
+
+    fn odt_lang(&self, node: &Node) -> Option<String> {
+        self.props(node.attribute((TEXT_NS, "style-name"))).lang()
+    }
+}
+
+/// Value of the `w:val` attribute of a child element, e.g. `w:pStyle`.
+fn docx_val<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, W_NS, name)?.attribute((W_NS, "val"))
+}
+
+/// Properties set by a `w:rPr` element.
+fn docx_props(props: Option<Node>) -> TextProps {
+    let props = match props {
+        Some(props) => props,
+        None => return TextProps::default(),
+    };
+    TextProps {
+        lang: docx_val(&props, "lang").map(lang_code),
+        no_proof: child(&props, W_NS, "noProof").map(|no_proof| {
+            !matches!(
+                no_proof.attribute((W_NS, "val")),
+                Some("0") | Some("false") | Some("off")
+            )
+        }),
+    }
+}
+
+fn docx(document: &str, styles: Option<&str>) -> Result<Vec<Paragraph>, SpellError> {
+    let mut docx_styles = Styles::default();
+    if let Some(styles) = styles {
+        docx_styles.load_docx(&parse(styles)?);
+    }
+    let doc = parse(document)?;
+    let mut paragraphs = Vec::new();
+    for p in doc.descendants().filter(|n| is(n, W_NS, "p")) {
+        let mut paragraph = Paragraph::new(paragraphs.len());
+        // the `w:rPr` of `w:pPr` only applies to the paragraph mark, the runs
+        // inherit from the paragraph style then the defaults
+        let style = child(&p, W_NS, "pPr").and_then(|props| docx_val(&props, "pStyle"));
+        let p_props = docx_styles
+            .props(style.or(docx_styles.default_paragraph.as_deref()))
+            .or(docx_styles.default.clone());
+        let runs = p
+            .descendants()
+            .filter(|n| is(n, W_NS, "r") && n.ancestors().find(|a| is(a, W_NS, "p")) == Some(p));
+        for run in runs {
+            let props = child(&run, W_NS, "rPr");
+            let style = props.and_then(|props| docx_val(&props, "rStyle"));
+            let lang = docx_props(props)
+                .or(docx_styles.props(style))
+                .or(p_props.clone())
+                .lang();
+            for node in run.children().filter(Node::is_element) {
+                let text = match node.tag_name().name() {
+                    "t" => node.text().unwrap_or(""),
+                    "tab" => "\t",
+                    "br" | "cr" => "\n",
+                    _ => continue,
+                };
+                paragraph.push(text, lang.as_deref());
+            }
+        }
+        paragraphs.push(paragraph);
+    }
+    Ok(paragraphs)
+}
+
+fn odt_text(styles: &Styles, node: Node, lang: Option<&str>, paragraph: &mut Paragraph) {
+    for child in node.children() {
+        if child.is_text() {
+            paragraph.push(child.text().unwrap_or(""), lang);
+            continue;
+        }
+        if child.tag_name().namespace() != Some(TEXT_NS) {
+            continue;
+        }
+        match child.tag_name().name() {
+            "s" => {
+                let count = child
+                    .attribute((TEXT_NS, "c"))
+                    .and_then(|c| c.parse().ok())
+                    .unwrap_or(1);
+                paragraph.push(&" ".repeat(count), lang);
+            }
+            "tab" => paragraph.push("\t", lang),
+            "line-break" => paragraph.push("\n", lang),
+            // notes are paragraphs on their own
+            "note" => {}
+            _ => {
+                let inner = styles.odt_lang(&child);
+                odt_text(styles, child, inner.as_deref().or(lang), paragraph);
+            }
+        }
+    }
+}
+
+fn odt(content: &str, styles: Option<&str>) -> Result<Vec<Paragraph>, SpellError> {
+    let mut odt_styles = Styles::default();
+    if let Some(styles) = styles {
+        odt_styles.load_odt(&parse(styles)?);
+    }
+    let doc = parse(content)?;
+    odt_styles.load_odt(&doc);
+    let body = match doc.descendants().find(|n| is(n, OFFICE_NS, "text")) {
+        Some(body) => body,
+        None => return Ok(Vec::new()),
+    };
+    let mut paragraphs = Vec::new();
+    for p in body
+        .descendants()
+        .filter(|n| is(n, TEXT_NS, "p") || is(n, TEXT_NS, "h"))
+    {
+        let mut paragraph = Paragraph::new(paragraphs.len());
+        let lang = odt_styles
+            .odt_lang(&p)
+            .or_else(|| odt_styles.default.clone().lang());
+        odt_text(&odt_styles, p, lang.as_deref(), &mut paragraph);
+        paragraphs.push(paragraph);
+    }
+    Ok(paragraphs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::zip;
+    use std::io::Cursor;
+
+    fn archive(members: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        Cursor::new(zip(members))
+    }
+
+    #[test]
+    fn docx_paragraphs() {
+        let document = r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+            <w:p><w:r><w:t>Hello </w:t></w:r><w:r><w:rPr><w:lang w:val="fr-FR"/></w:rPr><w:t>mon</w:t></w:r><w:r><w:rPr><w:lang w:val="fr-FR"/></w:rPr><w:t xml:space="preserve"> ami</w:t></w:r></w:p>
+            <w:p><w:r><w:rPr><w:lang w:val="de-DE"/></w:rPr><w:t>Hallo</w:t><w:tab/><w:instrText>HYPERLINK</w:instrText></w:r></w:p>
+        </w:body></w:document>"#;
+        let styles = r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:docDefaults><w:rPrDefault><w:rPr><w:lang w:val="en-US"/></w:rPr></w:rPrDefault></w:docDefaults></w:styles>"#;
+        let paragraphs = read_archive(archive(&[
+            ("word/document.xml", document),
+            ("word/styles.xml", styles),
+        ]))
+        .unwrap();
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0].text, "Hello mon ami");
+        assert_eq!(paragraphs[0].spans.len(), 2);
+        assert_eq!(paragraphs[0].spans[0].lang.as_deref(), Some("en_US"));
+        assert_eq!(
+            &paragraphs[0].text[paragraphs[0].spans[1].start..],
+            "mon ami"
+        );
+        assert_eq!(paragraphs[1].text, "Hallo\t");
+        assert_eq!(paragraphs[1].spans[0].lang.as_deref(), Some("de_DE"));
+        assert_eq!(languages(&paragraphs), vec!["de_DE", "en_US", "fr_FR"]);
+    }
+
+    #[test]
+    fn docx_styles() {
+        let document = r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+            <w:p><w:r><w:t>Bonjour </w:t></w:r><w:r><w:rPr><w:rStyle w:val="Code"/></w:rPr><w:t>let x</w:t></w:r><w:r><w:rPr><w:noProof/></w:rPr><w:t> xyzzy</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Quote"/><w:rPr><w:noProof/><w:lang w:val="it-IT"/></w:rPr></w:pPr><w:r><w:t>Hallo</w:t></w:r><w:r><w:rPr><w:lang w:val="en-GB"/></w:rPr><w:t> colour</w:t></w:r></w:p>
+            <w:p><w:r><w:rPr><w:rStyle w:val="Code"/><w:noProof w:val="0"/></w:rPr><w:t>checked</w:t></w:r><w:r><w:rPr><w:rStyle w:val="Code"/><w:lang w:val="en-GB"/></w:rPr><w:t> code</w:t></w:r></w:p>
+        </w:body></w:document>"#;
+        let styles = r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+            <w:docDefaults><w:rPrDefault><w:rPr><w:lang w:val="en-US"/></w:rPr></w:rPrDefault></w:docDefaults>
+            <w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:rPr><w:lang w:val="fr-FR"/></w:rPr></w:style>
+            <w:style w:type="paragraph" w:styleId="German"><w:basedOn w:val="Normal"/><w:rPr><w:lang w:val="de-DE"/></w:rPr></w:style>
+            <w:style w:type="paragraph" w:styleId="Quote"><w:basedOn w:val="German"/></w:style>
+            <w:style w:type="character" w:styleId="Code"><w:rPr><w:noProof/></w:rPr></w:style>
+        </w:styles>"#;
+        let paragraphs = read_archive(archive(&[
+            ("word/document.xml", document),
+            ("word/styles.xml", styles),
+        ]))
+        .unwrap();
+        let spans = |i: usize| -> Vec<(&str, Option<&str>)> {
+            let p = &paragraphs[i];
+            p.spans
+                .iter()
+                .map(|s| (&p.text[s.start..s.end], s.lang.as_deref()))
+                .collect()
+        };
+        assert_eq!(
+            spans(0),
+            vec![("Bonjour ", Some("fr_FR")), ("let x xyzzy", Some("zxx"))]
+        );
+        assert_eq!(
+            spans(1),
+            vec![("Hallo", Some("de_DE")), (" colour", Some("en_GB"))]
+        );
+        // properties are inherited one by one
+        assert_eq!(
+            spans(2),
+            vec![("checked", Some("fr_FR")), (" code", Some("zxx"))]
+        );
+    }
+
+    #[test]
+    fn odt_paragraphs() {
+        let content = r#"<office:document-content
+            xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0"
+            xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0"
+            xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0"
+            xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0">
+            <office:automatic-styles>
+                <style:style style:name="T1" style:family="text"><style:text-properties fo:language="fr" fo:country="FR"/></style:style>
+                <style:style style:name="P1" style:family="paragraph" style:parent-style-name="Code"/>
+            </office:automatic-styles>
+            <office:body><office:text>
+                <text:h>A title</text:h>
+                <text:p>Some<text:s text:c="2"/><text:span text:style-name="T1">texte</text:span><text:note><text:note-body><text:p>A note</text:p></text:note-body></text:note></text:p>
+                <text:p text:style-name="P1">let x</text:p>
+            </office:text></office:body>
+        </office:document-content>"#;
+        let styles = r#"<office:document-styles
+            xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0"
+            xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0"
+            xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0">
+            <office:styles>
+                <style:default-style style:family="paragraph"><style:text-properties fo:language="en" fo:country="GB"/></style:default-style>
+                <style:style style:name="Code" style:family="paragraph"><style:text-properties fo:language="zxx" fo:country="none"/></style:style>
+            </office:styles>
+        </office:document-styles>"#;
+        let paragraphs =
+            read_archive(archive(&[("content.xml", content), ("styles.xml", styles)])).unwrap();
+        let texts: Vec<&str> = paragraphs.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(texts, vec!["A title", "Some  texte", "A note", "let x"]);
+        assert_eq!(paragraphs[1].spans[0].lang.as_deref(), Some("en_GB"));
+        assert_eq!(paragraphs[1].spans[1].lang.as_deref(), Some("fr_FR"));
+        assert_eq!(paragraphs[3].spans[0].lang.as_deref(), Some("zxx"));
+        assert_eq!(languages(&paragraphs), vec!["en_GB", "fr_FR"]);
+    }
+
+    #[test]
+    fn excerpt() {
+        let mut paragraph = Paragraph::new(0);
+        paragraph.push(
+            "Some words before, a rather long paragraph with a mistaek somewhere in the middle of it, then more.",
+            None,
+        );
+        let offset = paragraph.text.find("mistaek").unwrap();
+        assert_eq!(
+            paragraph.excerpt(offset, 7),
+            "rather long paragraph with a mistaek somewhere in the middle of it"
+        );
+    }
+}
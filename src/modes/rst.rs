@@ -0,0 +1,245 @@
+//! reStructuredText mode.
+//!
+//! Directives, comments, literal blocks, doctests, roles, interpreted text,
+//! inline literals, substitution references and URLs are skipped. The content
+//! of admonitions and the text of hyperlink references are kept.
+
+use super::{at_word_start, indent, lines, url_len, Chunk, Spans};
+
+const ADMONITIONS: &[&str] = &[
+    "admonition",
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "seealso",
+    "tip",
+    "todo",
+    "versionadded",
+    "versionchanged",
+    "deprecated",
+    "warning",
+];
+
+#[derive(Clone, Copy)]
+enum State {
+    Normal,
+    /// Skips lines indented deeper than the given level.
+    SkipBlock(usize),
+    /// Skips the options of a directive, then goes back to normal.
+    Options(usize),
+    /// A literal block may follow, indented deeper than the given level.
+    Literal(usize),
+    /// Skips lines until a blank one.
+    SkipParagraph,
+}
+
+pub(super) fn chunks(text: &str) -> Vec<Chunk<'_>> {
+    let mut out = Vec::new();
+    let mut state = State::Normal;
+    for (offset, line) in lines(text) {
+        let blank = line.trim().is_empty();
+        let level = indent(line);
+        state = match state {
+            State::SkipBlock(n) if blank || level > n => continue,
+            State::Options(n) if !blank && level > n && line.trim_start().starts_with(':') => {
+                continue
+            }
+            State::Literal(n) if blank => State::Literal(n),
+            State::Literal(n) if level > n => State::SkipBlock(n),
+            State::SkipParagraph if !blank => continue,
+            _ => State::Normal,
+        };
+        if let State::SkipBlock(_) = state {
+            continue;
+        }
+        if blank {
+            continue;
+        }
+        let start = offset + level;
+        let content = line.trim();
+        if content == ".." || content.starts_with(".. ") {
+            state = explicit_markup(&mut out, start, content, level);
+        } else if content.starts_with(">>>") {
+            state = State::SkipParagraph;
+        } else {
+            if content.ends_with("::") {
+                state = State::Literal(level);
+            }
+            prose(&mut out, start, content);
+        }
+    }
+    out
+}
+
+fn explicit_markup<'a>(
+    out: &mut Vec<Chunk<'a>>,
+    offset: usize,
+    content: &'a str,
+    level: usize,
+) -> State {
+    let rest = content[2..].trim_start();
+    let rest_offset = offset + content.len() - rest.len();
+    if rest.starts_with('[') {
+        // footnotes and citations
+        if let Some(end) = rest.find(']') {
+            let body = &rest[end + 1..];
+            prose(out, rest_offset + end + 1, body);
+            return State::Normal;
+        }
+    }
+    if let Some(end) = rest.find("::") {
+        let name = &rest[..end];
+        if ADMONITIONS.contains(&name) {
+            prose(out, rest_offset + end + 2, &rest[end + 2..]);
+            return State::Options(level);
+        }
+    }
+    // other directives, substitution definitions, targets and comments
+    State::SkipBlock(level)
+}
+
+/// Length of a role name followed by a colon, e.g. `py:func:`.
+fn role_len(s: &str) -> Option<usize> {
+    let end = s.find(|c: char| !(c.is_alphanumeric() || "-_.+:".contains(c)))?;
+    let name = &s[..end];
+    if name.len() > 1 && name.ends_with(':') {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+/// Finds `needle` in `line` after `from`, returning its absolute index.
+fn find_from(line: &str, from: usize, needle: &str) -> Option<usize> {
+    line[from..].find(needle).map(|i| from + i)
+}
+
+fn prose<'a>(out: &mut Vec<Chunk<'a>>, offset: usize, line: &'a str) {
+    let mut spans = Spans::new(out, offset, line);
+    // field names at the beginning of a line
+    if let Some(field) = line.strip_prefix(':') {
+        if let Some(len) = role_len(field) {
+            let after = &line[len + 1..];
+            if after.is_empty() || after.starts_with(char::is_whitespace) {
+                spans.skip(0, len + 1);
+            }
+        }
+    }
+    let mut i = spans.start;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        let skip_to = if !at_word_start(line, i) {
+            if c == '_' {
+                // reference names, e.g. `Python_`
+                Some(i + rest.len() - rest.trim_start_matches('_').len())
+            } else {
+                None
+            }
+        } else if rest.starts_with("``") {
+            find_from(line, i + 2, "``").map(|end| end + 2)
+        } else if c == ':' {
+            role_len(&rest[1..])
+                .filter(|len| rest[len + 1..].starts_with('`'))
+                .and_then(|len| find_from(line, i + len + 2, "`"))
+                .map(|end| end + 1)
+        } else if c == '`' {
+            find_from(line, i + 1, "`").map(|end| {
+                let after = &line[end + 1..];
+                if after.starts_with('_') {
+                    // hyperlink reference, the text is prose but not the target
+                    let target = find_from(line, i + 1, "<").filter(|&t| t < end);
+                    spans.skip(i, i + 1);
+                    spans.keep(target.unwrap_or(end));
+                    end + 1 + after.len() - after.trim_start_matches('_').len()
+                } else if let Some(role) = after.strip_prefix(':') {
+                    end + 1 + role_len(role).map_or(0, |len| len + 1)
+                } else {
+                    end + 1
+                }
+            })
+        } else if c == '|' {
+            find_from(line, i + 1, "|")
+                .filter(|&end| {
+                    end > i + 1
+                        && !line[i + 1..end].starts_with(char::is_whitespace)
+                        && !line[i + 1..end].ends_with(char::is_whitespace)
+                })
+                .map(|end| {
+                    end + 1 + line[end + 1..].len() - line[end + 1..].trim_start_matches('_').len()
+                })
+        } else if c == '[' {
+            find_from(line, i + 1, "]_").map(|end| end + 2)
+        } else {
+            url_len(rest).map(|len| i + len)
+        };
+        match skip_to {
+            Some(end) => {
+                spans.skip(i, end);
+                i = end;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    spans.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(doc: &str) -> Vec<&str> {
+        chunks(doc).into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn inline_markup() {
+        let doc = "Use :func:`spell.check` or ``check_word`` with |name| and `Python <https://python.org>`_.";
+        assert_eq!(texts(doc).concat(), "Use  or  with  and Python .");
+    }
+
+    #[test]
+    fn blocks() {
+        let doc = "\
+Title
+=====
+
+.. code-block:: rust
+
+   let wrod = 1;
+
+Example::
+
+    fn mian() {}
+
+.. note::
+   :class: tip
+
+   Some advise.
+
+:Author: Someone
+";
+        let found = texts(doc).concat();
+        assert!(!found.contains("wrod"));
+        assert!(!found.contains("mian"));
+        assert!(!found.contains("class"));
+        assert!(found.contains("Some advise."));
+        assert!(found.contains(" Someone"));
+        assert!(!found.contains("Author"));
+    }
+
+    #[test]
+    fn offsets() {
+        let doc = "A ``lit`` word";
+        for chunk in chunks(doc) {
+            assert_eq!(
+                &doc[chunk.offset..chunk.offset + chunk.text.len()],
+                chunk.text
+            );
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Persistent personal word lists.
+//!
+//! Each list is a text file with one entry per line: a word to accept, a word
+//! followed by a tab and an example word whose affixes it shares, or a word
+//! prefixed by `*` to reject.
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::data;
+use crate::dirs::Dirs;
+use crate::hunspell::Hunspell;
+use crate::SpellError;
+use fs2::FileExt;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+    Word(String),
+    Like(String, String),
+    Forbidden(String),
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Entry> {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            None
+        } else if let Some(word) = line.strip_prefix('*') {
+            Some(Entry::Forbidden(word.to_string()))
+        } else if let Some(tab) = line.find('\t') {
+            Some(Entry::Like(
+                line[..tab].to_string(),
+                line[tab + 1..].to_string(),
+            ))
+        } else {
+            Some(Entry::Word(line.to_string()))
+        }
+    }
+
+    fn word(&self) -> &str {
+        match self {
+            Entry::Word(word) | Entry::Like(word, _) | Entry::Forbidden(word) => word,
+        }
+    }
+
+    fn line(&self) -> String {
+        match self {
+            Entry::Word(word) => word.clone(),
+            Entry::Like(word, example) => format!("{}\t{}", word, example),
+            Entry::Forbidden(word) => format!("*{}", word),
+        }
+    }
+
+    /// Checks that the entry reads back the same from its line: its words
+    /// are neither empty nor contain tabs or line breaks, and the word does
+    /// not start with `*`.
+    fn check(&self) -> io::Result<()> {
+        let words = match self {
+            Entry::Word(word) | Entry::Forbidden(word) => [word, word],
+            Entry::Like(word, example) => [word, example],
+        };
+        let invalid = words
+            .iter()
+            .any(|w| w.is_empty() || w.contains(['\t', '\n', '\r']))
+            || self.word().starts_with('*');
+        if invalid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid personal dictionary entry {:?}", self.line()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A list of words added or removed by the user, saved on the disk.
+///
+/// Changes are written atomically and serialized between processes with an
+/// advisory lock, so several processes can update the same list at once.
+pub struct PersonalDictionary {
+    path: PathBuf,
+}
+
+/// Adds a word with the affixes of `example`, or without affixes when the
+/// dictionary does not know `example`, e.g. the one of another language.
+pub(crate) fn add_like(hs: &Hunspell, word: &str, example: &str) -> bool {
+    hs.add_with_affix(word, example) || hs.add(word)
+}
+
+impl PersonalDictionary {
+    /// Creates a personal dictionary stored at the given path.
+    pub fn new<P: Into<PathBuf>>(path: P) -> PersonalDictionary {
+        PersonalDictionary { path: path.into() }
+    }
+
+    /// The personal dictionary of the given language.
+    pub fn for_lang(lang: &str) -> PersonalDictionary {
+        Self::in_dirs(&Dirs::from_env(), lang)
+    }
+
+    /// The personal dictionary shared by all languages.
+    pub fn global() -> PersonalDictionary {
+        Self::in_dirs(&Dirs::from_env(), "global")
+    }
+
+    /// The personal dictionary of the given name in the data directory.
+    pub(crate) fn in_dirs(dirs: &Dirs, name: &str) -> PersonalDictionary {
+        Self::new(dirs.personal().join(format!("{}.txt", name)))
+    }
+
+    /// Location of the word list.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> io::Result<Vec<Entry>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(content.lines().filter_map(Entry::parse).collect()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Applies the entries of the list to the given dictionary.
+    pub(crate) fn load_into(&self, hs: &Hunspell) -> Result<(), SpellError> {
+        let entries = self.read().map_err(SpellError::PersonalDictionaryError)?;
+        log::debug!(
+            "{} personal entries from {}",
+            entries.len(),
+            self.path.display()
+        );
+        for entry in entries {
+            match entry {
+                Entry::Word(word) => hs.add(&word),
+                Entry::Like(word, example) => add_like(hs, &word, &example),
+                Entry::Forbidden(word) => hs.remove(&word),
+            };
+        }
+        Ok(())
+    }
+
+    fn update(&self, entry: Entry) -> io::Result<()> {
+        entry.check()?;
+        let dir = match self.path.parent() {
+            Some(dir) if self.path.file_name().is_some() => dir,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid personal dictionary {}", self.path.display()),
+                ))
+            }
+        };
+        fs::create_dir_all(dir)?;
+        // released when dropped, once the list is written
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension("lock"))?;
+        lock.lock_exclusive()?;
+        let mut entries = self.read()?;
+        entries.retain(|e| e.word() != entry.word());
+        entries.push(entry);
+        let mut content = String::new();
+        for entry in &entries {
+            content.push_str(&entry.line());
+            content.push('\n');
+        }
+        data::write_atomic(&self.path, content.as_bytes())
+    }
+
+    /// Adds a word to the list.
+    ///
+    /// Fails for an empty word, a word with tabs or line breaks, or starting
+    /// with `*`.
+    pub fn learn(&self, word: &str) -> Result<(), SpellError> {
+        self.update(Entry::Word(word.to_string()))
+            .map_err(SpellError::PersonalDictionaryError)
+    }
+
+    /// Adds a word to the list, with the affixes of `example`.
+    pub fn learn_like(&self, word: &str, example: &str) -> Result<(), SpellError> {
+        self.update(Entry::Like(word.to_string(), example.to_string()))
+            .map_err(SpellError::PersonalDictionaryError)
+    }
+
+    /// Marks a word as incorrect, even if the main dictionary contains it.
+    pub fn forget(&self, word: &str) -> Result<(), SpellError> {
+        self.update(Entry::Forbidden(word.to_string()))
+            .map_err(SpellError::PersonalDictionaryError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::thread;
+
+    #[test]
+    fn learn_and_forget() {
+        let dir = TempDir::new("personal-learn");
+        let personal = PersonalDictionary::new(dir.join("learn.txt"));
+        personal.learn("fussoire").unwrap();
+        personal.learn_like("rustacean", "crustacean").unwrap();
+        personal.forget("coucou").unwrap();
+        personal.forget("fussoire").unwrap();
+        assert_eq!(
+            fs::read_to_string(personal.path()).unwrap(),
+            "rustacean\tcrustacean\n*coucou\n*fussoire\n"
+        );
+
+        let h = Hunspell::new("files/fr.aff", "files/fr.dic");
+        assert!(h.spell("coucou"));
+        personal.load_into(&h).unwrap();
+        assert!(!h.spell("coucou"));
+        assert!(h.spell("rustacean"));
+
+        // entries which would read back differently are rejected
+        for word in &["*coucou", "a\tb", "a\nb", ""] {
+            match personal.learn(word) {
+                Err(SpellError::PersonalDictionaryError(err)) => {
+                    assert_eq!(err.kind(), io::ErrorKind::InvalidInput)
+                }
+                other => panic!("unexpected result for {:?}: {:?}", word, other),
+            }
+        }
+        assert!(personal.learn_like("rustacean", "crab\n*coucou").is_err());
+        assert_eq!(
+            fs::read_to_string(personal.path()).unwrap(),
+            "rustacean\tcrustacean\n*coucou\n*fussoire\n"
+        );
+        for path in &["/", ""] {
+            assert!(PersonalDictionary::new(*path).learn("coucou").is_err());
+        }
+    }
+
+    #[test]
+    fn concurrent_updates() {
+        let dir = TempDir::new("personal-concurrent");
+        let path = dir.join("concurrent.txt");
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    PersonalDictionary::new(path)
+                        .learn(&format!("word{}", i))
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 8);
+    }
+}
@@ -0,0 +1,201 @@
+//! Which dictionary files are used and where they come from.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::data::{InstallInfo, SourceInfo};
+use crate::download;
+use crate::encoding;
+use crate::SpellError;
+
+/// Where a dictionary file comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    /// Shipped with the crate, used with the `local_files` feature.
+    Bundled,
+    /// Copied from a directory of the system.
+    System(PathBuf),
+    /// Downloaded from a URL.
+    Download(String),
+    /// Extracted from a zip archive, at the given path or URL.
+    Archive { archive: String, member: String },
+    /// Installed without metadata, e.g. by an older version.
+    Unknown,
+}
+
+impl Origin {
+    fn from_source(source: &SourceInfo) -> Origin {
+        let source = source.source.as_str();
+        if let Some((archive, member)) = source.rsplit_once('#') {
+            Origin::Archive {
+                archive: archive.to_string(),
+                member: member.to_string(),
+            }
+        } else if source.contains("://") {
+            Origin::Download(source.to_string())
+        } else {
+            Origin::System(PathBuf::from(source))
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Bundled => f.write_str("bundled"),
+            Origin::System(path) => write!(f, "system {}", path.display()),
+            Origin::Download(url) => write!(f, "download {}", url),
+            Origin::Archive { archive, member } => write!(f, "archive {} ({})", archive, member),
+            Origin::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Where the files of a dictionary are read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Location {
+    /// The files shipped with the crate.
+    Bundled,
+    /// The data directory, where they are installed.
+    Installed,
+    /// A directory of the system, used in place.
+    System,
+}
+
+/// A dictionary file used by a spellchecker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileProvenance {
+    /// Location of the file read by hunspell.
+    pub path: PathBuf,
+    /// Where the file comes from.
+    pub origin: Origin,
+    /// SHA-256 digest of the file read by hunspell, in hexadecimal.
+    pub sha256: String,
+    /// SHA-256 digest of the source file, before its conversion to UTF-8.
+    pub source_sha256: Option<String>,
+}
+
+/// The files of a dictionary used by a spellchecker, see
+/// [Spell::provenance()](struct.Spell.html#method.provenance).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// Name of the dictionary.
+    pub lang: String,
+    /// The `.aff` file.
+    pub aff: FileProvenance,
+    /// The `.dic` file.
+    pub dic: FileProvenance,
+    /// Encoding of the files read by hunspell, as declared by the `.aff`.
+    pub encoding: String,
+    /// Encoding of the sources, converted to UTF-8 when installed.
+    pub source_encoding: Option<String>,
+    /// Install date, in seconds since the Unix epoch.
+    pub installed: Option<u64>,
+}
+
+impl Provenance {
+    /// Provenance of the files of a dictionary, from their install metadata
+    /// when they are installed.
+    pub(crate) fn of(
+        lang: &str,
+        aff: &Path,
+        dic: &Path,
+        location: Location,
+    ) -> Result<Provenance, SpellError> {
+        let info = match location {
+            Location::Installed => {
+                InstallInfo::read(&InstallInfo::path(aff.parent().unwrap(), lang))
+            }
+            _ => None,
+        };
+        let raw_aff = fs::read(aff).map_err(SpellError::ReadDictionaryError)?;
+        let raw_dic = fs::read(dic).map_err(SpellError::ReadDictionaryError)?;
+        let file = |path: &Path, raw: &[u8], source: Option<&SourceInfo>| FileProvenance {
+            path: path.to_owned(),
+            origin: match (source, location) {
+                (Some(source), _) => Origin::from_source(source),
+                (None, Location::Bundled) => Origin::Bundled,
+                (None, Location::System) => Origin::System(path.to_owned()),
+                (None, Location::Installed) => Origin::Unknown,
+            },
+            sha256: download::sha256_hex(raw),
+            source_sha256: source.map(|source| source.sha256.clone()),
+        };
+        Ok(Provenance {
+            lang: lang.to_string(),
+            aff: file(aff, &raw_aff, info.as_ref().map(|info| &info.aff)),
+            dic: file(dic, &raw_dic, info.as_ref().map(|info| &info.dic)),
+            encoding: encoding::detect_encoding(&raw_aff)?.name().to_string(),
+            source_encoding: info
+                .as_ref()
+                .map(|info| info.encoding.clone())
+                .filter(|encoding| !encoding.is_empty()),
+            installed: info.map(|info| info.installed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn origins() {
+        let tmp = TempDir::new("provenance");
+        let dir = tmp.path().to_owned();
+        let (aff, dic) = (dir.join("fr.aff"), dir.join("fr.dic"));
+        fs::copy("files/fr.aff", &aff).unwrap();
+        fs::copy("files/fr.dic", &dic).unwrap();
+
+        let unknown = Provenance::of("fr", &aff, &dic, Location::Installed).unwrap();
+        assert_eq!(unknown.aff.origin, Origin::Unknown);
+        assert_eq!(
+            unknown.aff.sha256,
+            download::sha256_hex(&fs::read(&aff).unwrap())
+        );
+        assert_eq!(unknown.encoding, "UTF-8");
+        assert_eq!(unknown.installed, None);
+        let bundled = Provenance::of("fr", &aff, &dic, Location::Bundled).unwrap();
+        assert_eq!(bundled.dic.origin, Origin::Bundled);
+        let system = Provenance::of("fr", &aff, &dic, Location::System).unwrap();
+        assert_eq!(system.aff.origin, Origin::System(aff.clone()));
+
+        let info = InstallInfo {
+            installed: 1,
+            encoding: String::from("ISO-8859-1"),
+            lossy: false,
+            aff: SourceInfo {
+                source: String::from("https://example.org/fr.aff"),
+                sha256: String::from("00"),
+                ..SourceInfo::default()
+            },
+            dic: SourceInfo {
+                source: String::from("/usr/share/hunspell/fr.dic"),
+                ..SourceInfo::default()
+            },
+        };
+        info.write(&InstallInfo::path(&dir, "fr")).unwrap();
+        let installed = Provenance::of("fr", &aff, &dic, Location::Installed).unwrap();
+        assert_eq!(
+            installed.aff.origin,
+            Origin::Download(String::from("https://example.org/fr.aff"))
+        );
+        assert_eq!(installed.aff.source_sha256.as_deref(), Some("00"));
+        assert_eq!(
+            installed.dic.origin,
+            Origin::System(PathBuf::from("/usr/share/hunspell/fr.dic"))
+        );
+        assert_eq!(installed.source_encoding.as_deref(), Some("ISO-8859-1"));
+        assert_eq!(installed.installed, Some(1));
+        assert_eq!(
+            Origin::from_source(&SourceInfo {
+                source: String::from("dict-fr.oxt#fr.aff"),
+                ..SourceInfo::default()
+            })
+            .to_string(),
+            "archive dict-fr.oxt (fr.aff)"
+        );
+    }
+}
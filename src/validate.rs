@@ -0,0 +1,357 @@
+//! Checks of config files, beyond what loading them requires.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::{Locale, SpellError};
+use directories::BaseDirs;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use toml::Spanned;
+
+const TOP_KEYS: &[&str] = &["lang", "ignore", "words", "dictionaries"];
+const DICTIONARIES_KEYS: &[&str] = &[
+    "directories",
+    "user_directories",
+    "sources",
+    "system_in_place",
+];
+const SOURCE_KEYS: &[&str] = &["aff", "dic", "archive", "aff_sha256", "dic_sha256"];
+
+/// How serious a config issue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The configuration is most likely wrong.
+    Error,
+    /// The configuration works but may not be what is expected, e.g. a
+    /// directory absent on this machine.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// An issue found in a config file, see
+/// [Config::validate()](struct.Config.html#method.validate).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// How serious the issue is.
+    pub severity: Severity,
+    /// Line of the issue, starting at 1.
+    pub line: usize,
+    /// Column of the issue in characters, starting at 1.
+    pub column: usize,
+    /// Description of the issue.
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+/// A TOML value with the locations of its keys and values.
+enum Node {
+    Table(Vec<(Spanned<String>, Spanned<Node>)>),
+    Array(Vec<Spanned<Node>>),
+    Str(String),
+    Other,
+}
+
+impl Node {
+    fn entries(&self) -> &[(Spanned<String>, Spanned<Node>)] {
+        match self {
+            Node::Table(entries) => entries,
+            _ => &[],
+        }
+    }
+
+    fn items(&self) -> &[Spanned<Node>] {
+        match self {
+            Node::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Spanned<Node>> {
+        self.entries()
+            .iter()
+            .find(|(k, _)| k.get_ref() == key)
+            .map(|(_, value)| value)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a TOML value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Node, E> {
+        Ok(Node::Other)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Node, E> {
+        Ok(Node::Other)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Node, E> {
+        Ok(Node::Other)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Node, E> {
+        Ok(Node::Other)
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Node, E> {
+        Ok(Node::Str(value.to_string()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(key) = map.next_key()? {
+            entries.push((key, map.next_value()?));
+        }
+        Ok(Node::Table(entries))
+    }
+}
+
+struct Checker<'a> {
+    raw: &'a str,
+    issues: Vec<ConfigIssue>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, severity: Severity, offset: usize, message: String) {
+        let before = &self.raw[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        self.issues.push(ConfigIssue {
+            severity,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        });
+    }
+
+    fn known_keys(&mut self, node: &Node, table: &str, known: &[&str]) {
+        for (key, _) in node.entries() {
+            if !known.contains(&key.get_ref().as_str()) {
+                self.push(
+                    Severity::Error,
+                    key.start(),
+                    format!("unknown key `{}` in {}", key.get_ref(), table),
+                );
+            }
+        }
+    }
+
+    fn directories(&mut self, dicts: &Node) {
+        let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_owned());
+        for (field, relative) in &[("directories", false), ("user_directories", true)] {
+            let items = dicts
+                .get(field)
+                .map_or(&[][..], |items| items.get_ref().items());
+            for item in items {
+                let dir = match item.get_ref().as_str() {
+                    Some(dir) => dir,
+                    None => continue,
+                };
+                let path = match (&home, relative) {
+                    (Some(home), true) => home.join(dir),
+                    (None, true) => continue,
+                    (_, false) => PathBuf::from(dir),
+                };
+                if !path.is_dir() {
+                    self.push(
+                        Severity::Warning,
+                        item.start(),
+                        format!("directory `{}` does not exist", path.display()),
+                    );
+                }
+            }
+        }
+    }
+
+    fn sources(&mut self, sources: &Node) {
+        let mut langs = HashMap::new();
+        let mut files = HashMap::new();
+        for (lang, source) in sources.entries() {
+            let lang_name = lang.get_ref().as_str();
+            let table = format!("dictionaries.sources.{}", lang_name);
+            self.known_keys(source.get_ref(), &table, SOURCE_KEYS);
+            let normalized =
+                Locale::parse(lang_name).map_or_else(|| lang_name.to_string(), |l| l.to_string());
+            if let Some(other) = langs.insert(normalized, lang_name) {
+                self.push(
+                    Severity::Error,
+                    lang.start(),
+                    format!("language `{}` is already defined as `{}`", lang_name, other),
+                );
+            }
+            let archive = source
+                .get_ref()
+                .get("archive")
+                .and_then(|archive| archive.get_ref().as_str())
+                .unwrap_or("");
+            for field in &["aff", "dic"] {
+                let (value, location) = match source.get_ref().get(field) {
+                    Some(value) => match value.get_ref().as_str() {
+                        Some(s) if !s.is_empty() => (s, value.start()),
+                        _ => continue,
+                    },
+                    None => continue,
+                };
+                if let Some(other) = files.insert((archive, value), lang_name) {
+                    self.push(
+                        Severity::Error,
+                        location,
+                        format!(
+                            "`{}` uses the same .{} file as `{}`",
+                            lang_name, field, other
+                        ),
+                    );
+                }
+                let file_name = value.rsplit('/').next().unwrap();
+                let stem = file_name
+                    .rsplit_once('.')
+                    .map_or(file_name, |(stem, _)| stem);
+                if !matches_lang(lang_name, stem) {
+                    self.push(
+                        Severity::Error,
+                        location,
+                        format!(
+                            "file name `{}` does not match language `{}`",
+                            file_name, lang_name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Whether a dictionary file name is one of the language: it starts with the
+/// language subtag, e.g. `fr.aff` for `fr_FR` or `catalan.aff` for `ca`.
+fn matches_lang(lang: &str, stem: &str) -> bool {
+    let language = Locale::parse(lang).map_or_else(|| lang.to_ascii_lowercase(), |l| l.language);
+    stem.to_ascii_lowercase().starts_with(&language)
+}
+
+impl Config {
+    /// Checks the content of a config file, beyond what loading it requires.
+    ///
+    /// It detects unknown keys, languages defined twice or sharing their
+    /// files, file names not matching their language and missing directories.
+    /// The issues are sorted by location, an error is returned when the file
+    /// cannot be loaded at all.
+    pub fn validate(raw: &str) -> Result<Vec<ConfigIssue>, SpellError> {
+        toml::from_str::<Config>(raw).map_err(SpellError::LoadConfigError)?;
+        let root: Node = toml::from_str(raw).map_err(SpellError::LoadConfigError)?;
+        let mut checker = Checker {
+            raw,
+            issues: Vec::new(),
+        };
+        checker.known_keys(&root, "the top level", TOP_KEYS);
+        if let Some(dicts) = root.get("dictionaries") {
+            let dicts = dicts.get_ref();
+            checker.known_keys(dicts, "dictionaries", DICTIONARIES_KEYS);
+            checker.directories(dicts);
+            if let Some(sources) = dicts.get("sources") {
+                checker.sources(sources.get_ref());
+            }
+        }
+        let mut issues = checker.issues;
+        issues.sort_by_key(|issue| (issue.line, issue.column));
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in() {
+        let issues = Config::validate(include_str!("../files/config.toml")).unwrap();
+        let errors: Vec<_> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn issues() {
+        let raw = r#"lang = "fr"
+langs = "en"
+[dictionaries]
+directories = ["/nonexistent/rspell/"]
+[dictionaries.sources.fr_FR]
+aff = "https://example.org/fr_FR/fr.aff"
+dic = "https://example.org/fr_FR/fr.dic"
+[dictionaries.sources.fr-fr]
+aff = "https://example.org/fr_FR/fr.aff"
+dic = "https://example.org/fr-fr/fr.dic"
+[dictionaries.sources.ar]
+aff = "https://example.org/ar/fr.aff"
+dic = "https://example.org/ar/ar.dic"
+sha256 = "00"
+"#;
+        let issues: Vec<String> = Config::validate(raw)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "2:1: error: unknown key `langs` in the top level",
+                "4:16: warning: directory `/nonexistent/rspell/` does not exist",
+                "8:23: error: language `fr-fr` is already defined as `fr_FR`",
+                "9:7: error: `fr-fr` uses the same .aff file as `fr_FR`",
+                "12:7: error: file name `fr.aff` does not match language `ar`",
+                "14:1: error: unknown key `sha256` in dictionaries.sources.ar",
+            ]
+        );
+        match Config::validate("lang = [") {
+            Err(SpellError::LoadConfigError(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
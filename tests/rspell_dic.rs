@@ -0,0 +1,40 @@
+#![cfg(feature = "tools")]
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn config_check_broken() {
+    let base = std::env::temp_dir().join(format!("rspell-dic-check-{}", std::process::id()));
+    fs::create_dir_all(&base).unwrap();
+    let file = base.join("broken.toml");
+    fs::write(&file, "lang = [\n").unwrap();
+
+    let check = |command: &mut Command| {
+        let output = command
+            .env("HOME", &base)
+            .env("XDG_CONFIG_HOME", base.join("config"))
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.starts_with(&format!("{}: error: ", file.display())),
+            "{}",
+            stdout
+        );
+    };
+    check(
+        Command::new(env!("CARGO_BIN_EXE_rspell-dic"))
+            .args(["config", "check"])
+            .arg(&file),
+    );
+    check(
+        Command::new(env!("CARGO_BIN_EXE_rspell-dic"))
+            .args(["config", "check"])
+            .env("RSPELL_CONFIG", &file),
+    );
+    // the user config file is not created
+    assert!(!base.join("config").exists());
+    fs::remove_dir_all(&base).unwrap();
+}